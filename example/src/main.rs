@@ -39,7 +39,7 @@ async fn main() {
         .swap(
             &SwapRequest {
                 user_public_key: TEST_WALLET,
-                quote_response: quote_response.clone(),
+                quote_response: quote_response.clone().into(),
                 config: TransactionConfig::default(),
             },
             None,
@@ -71,7 +71,7 @@ async fn main() {
     let swap_instructions = jupiter_swap_api_client
         .swap_instructions(&SwapRequest {
             user_public_key: TEST_WALLET,
-            quote_response,
+            quote_response: quote_response.into(),
             config: TransactionConfig::default(),
         })
         .await
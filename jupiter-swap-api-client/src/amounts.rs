@@ -0,0 +1,198 @@
+//! Helpers for raw token amounts: conversion from and to UI amounts and slippage
+//!
+
+use rust_decimal::{Decimal, RoundingStrategy};
+
+/// Convert a raw token amount into its UI amount given the mint decimals.
+/// `None` when `decimals` exceeds the maximum `Decimal` scale of 28.
+pub fn base_to_ui_amount(amount: u64, decimals: u8) -> Option<Decimal> {
+    Decimal::try_from_i128_with_scale(i128::from(amount), u32::from(decimals)).ok()
+}
+
+/// How [`ui_amount_to_base`] rounds UI amounts with more decimal places than the mint supports, and
+/// how [`QuoteResponse::price_impact_rounded`](crate::quote::QuoteResponse::price_impact_rounded)
+/// rounds to its scale
+#[derive(Debug, Default, PartialEq, Eq, Clone, Copy)]
+pub enum RoundingMode {
+    /// Round down, so an amount to spend is never exceeded
+    #[default]
+    Floor,
+    /// Round up
+    Ceil,
+    /// Round to the nearest raw unit, halfway values away from zero, as for display
+    HalfUp,
+}
+
+impl RoundingMode {
+    pub(crate) fn strategy(self) -> RoundingStrategy {
+        match self {
+            RoundingMode::Floor => RoundingStrategy::ToNegativeInfinity,
+            RoundingMode::Ceil => RoundingStrategy::ToPositiveInfinity,
+            RoundingMode::HalfUp => RoundingStrategy::MidpointAwayFromZero,
+        }
+    }
+}
+
+/// Convert a UI amount into a raw token amount given the mint decimals, rounding excess decimal
+/// places with `rounding`. `None` when the result is negative or does not fit in a `u64`.
+pub fn ui_amount_to_base(ui_amount: Decimal, decimals: u8, rounding: RoundingMode) -> Option<u64> {
+    let decimals = u32::from(decimals);
+    let mut amount = ui_amount.round_dp_with_strategy(decimals, rounding.strategy());
+    amount.rescale(decimals);
+    if amount.scale() != decimals {
+        return None;
+    }
+    u64::try_from(amount.mantissa()).ok()
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum SlippageDirection {
+    /// Reduce the amount, rounding down, as for the minimum output of an ExactIn swap
+    MinimumOut,
+    /// Increase the amount, rounding up, as for the maximum input of an ExactOut swap
+    MaximumIn,
+}
+
+/// Apply `slippage_bps` to `amount`, rounding against the user so the result is never more favourable
+/// than the exact value. Saturates at `0` and `u64::MAX`.
+pub fn apply_slippage(amount: u64, slippage_bps: u16, direction: SlippageDirection) -> u64 {
+    let amount = u128::from(amount);
+    let slippage_bps = u128::from(slippage_bps);
+    let result = match direction {
+        SlippageDirection::MinimumOut => amount * 10_000u128.saturating_sub(slippage_bps) / 10_000,
+        SlippageDirection::MaximumIn => (amount * (10_000 + slippage_bps)).div_ceil(10_000),
+    };
+    u64::try_from(result).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn base_to_ui_amount_scales_by_decimals() {
+        assert_eq!(base_to_ui_amount(1_500_000, 6), Some(Decimal::new(15, 1)));
+        assert_eq!(
+            base_to_ui_amount(u64::MAX, 0),
+            Some(Decimal::from(u64::MAX))
+        );
+        assert_eq!(
+            base_to_ui_amount(1, 28),
+            Some(Decimal::from_i128_with_scale(1, 28))
+        );
+    }
+
+    #[test]
+    fn base_to_ui_amount_rejects_unsupported_decimals() {
+        assert_eq!(base_to_ui_amount(1, 29), None);
+        assert_eq!(base_to_ui_amount(1, u8::MAX), None);
+    }
+
+    #[test]
+    fn apply_slippage_rounds_against_the_user() {
+        assert_eq!(
+            apply_slippage(1_999, 50, SlippageDirection::MinimumOut),
+            1_989
+        );
+        assert_eq!(
+            apply_slippage(1_999, 50, SlippageDirection::MaximumIn),
+            2_009
+        );
+        assert_eq!(apply_slippage(1, 1, SlippageDirection::MinimumOut), 0);
+        assert_eq!(apply_slippage(1, 1, SlippageDirection::MaximumIn), 2);
+    }
+
+    #[test]
+    fn apply_slippage_is_exact_on_whole_results() {
+        assert_eq!(
+            apply_slippage(10_000, 50, SlippageDirection::MinimumOut),
+            9_950
+        );
+        assert_eq!(
+            apply_slippage(10_000, 50, SlippageDirection::MaximumIn),
+            10_050
+        );
+    }
+
+    #[test]
+    fn apply_slippage_without_slippage_keeps_the_amount() {
+        for direction in [SlippageDirection::MinimumOut, SlippageDirection::MaximumIn] {
+            assert_eq!(apply_slippage(0, 0, direction), 0);
+            assert_eq!(apply_slippage(12_345, 0, direction), 12_345);
+            assert_eq!(apply_slippage(u64::MAX, 0, direction), u64::MAX);
+        }
+    }
+
+    #[test]
+    fn apply_slippage_saturates() {
+        assert_eq!(
+            apply_slippage(12_345, 10_000, SlippageDirection::MinimumOut),
+            0
+        );
+        assert_eq!(
+            apply_slippage(12_345, u16::MAX, SlippageDirection::MinimumOut),
+            0
+        );
+        assert_eq!(
+            apply_slippage(12_345, 10_000, SlippageDirection::MaximumIn),
+            24_690
+        );
+        assert_eq!(
+            apply_slippage(u64::MAX, 1, SlippageDirection::MaximumIn),
+            u64::MAX
+        );
+        assert_eq!(
+            apply_slippage(u64::MAX, u16::MAX, SlippageDirection::MaximumIn),
+            u64::MAX
+        );
+        assert_eq!(
+            apply_slippage(u64::MAX, 1, SlippageDirection::MinimumOut),
+            u64::MAX / 10_000 * 9_999 + u64::MAX % 10_000 * 9_999 / 10_000
+        );
+    }
+
+    #[test]
+    fn ui_amount_to_base_rounds_with_each_mode_at_a_boundary() {
+        // Halfway between two raw units of a 6 decimals mint
+        let halfway = Decimal::new(10_000_005, 7);
+        assert_eq!(
+            ui_amount_to_base(halfway, 6, RoundingMode::Floor),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            ui_amount_to_base(halfway, 6, RoundingMode::Ceil),
+            Some(1_000_001)
+        );
+        assert_eq!(
+            ui_amount_to_base(halfway, 6, RoundingMode::HalfUp),
+            Some(1_000_001)
+        );
+
+        // Just below halfway, only Ceil rounds up
+        let below_halfway = Decimal::new(100_000_049, 8);
+        assert_eq!(
+            ui_amount_to_base(below_halfway, 6, RoundingMode::Floor),
+            Some(1_000_000)
+        );
+        assert_eq!(
+            ui_amount_to_base(below_halfway, 6, RoundingMode::Ceil),
+            Some(1_000_001)
+        );
+        assert_eq!(
+            ui_amount_to_base(below_halfway, 6, RoundingMode::HalfUp),
+            Some(1_000_000)
+        );
+
+        // Exact amounts are left untouched by every mode
+        for rounding in [
+            RoundingMode::Floor,
+            RoundingMode::Ceil,
+            RoundingMode::HalfUp,
+        ] {
+            assert_eq!(
+                ui_amount_to_base(Decimal::new(1_000_001, 6), 6, rounding),
+                Some(1_000_001)
+            );
+        }
+    }
+}
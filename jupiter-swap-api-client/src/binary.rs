@@ -0,0 +1,208 @@
+//! Compact binary encoding of quotes via bincode
+//!
+//! The JSON representation stringifies amounts and skips absent fields, which bincode cannot
+//! round-trip, so quotes are encoded through the mirror structs below. Unmodeled swap info fields
+//! are arbitrary JSON and are carried as a JSON string.
+
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    quote::{AppliedComputeUnitScore, PlatformFee, QuoteResponse, SwapInfo, SwapMode},
+    route_plan_with_metadata::RoutePlanStep,
+};
+
+#[derive(Serialize, Deserialize)]
+struct BinarySwapInfo {
+    amm_key: Pubkey,
+    label: String,
+    input_mint: Pubkey,
+    output_mint: Pubkey,
+    in_amount: u64,
+    out_amount: u64,
+    fee_amount: Option<u64>,
+    fee_mint: Option<Pubkey>,
+    extra: String,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryRoutePlanStep {
+    swap_info: BinarySwapInfo,
+    percent: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryPlatformFee {
+    amount: u64,
+    fee_bps: u8,
+}
+
+#[derive(Serialize, Deserialize)]
+struct BinaryQuoteResponse {
+    input_mint: Pubkey,
+    in_amount: u64,
+    output_mint: Pubkey,
+    out_amount: u64,
+    other_amount_threshold: u64,
+    swap_mode: SwapMode,
+    slippage_bps: u16,
+    computed_auto_slippage: Option<u16>,
+    uses_quote_minimizing_slippage: Option<bool>,
+    probe_amount: Option<u64>,
+    applied_compute_unit_score: Option<AppliedComputeUnitScore>,
+    platform_fee: Option<BinaryPlatformFee>,
+    price_impact_pct: [u8; 16],
+    route_plan: Vec<BinaryRoutePlanStep>,
+    context_slot: u64,
+    time_taken: f64,
+}
+
+impl From<&QuoteResponse> for BinaryQuoteResponse {
+    fn from(value: &QuoteResponse) -> Self {
+        Self {
+            input_mint: value.input_mint,
+            in_amount: value.in_amount,
+            output_mint: value.output_mint,
+            out_amount: value.out_amount,
+            other_amount_threshold: value.other_amount_threshold,
+            swap_mode: value.swap_mode.clone(),
+            slippage_bps: value.slippage_bps,
+            computed_auto_slippage: value.computed_auto_slippage,
+            uses_quote_minimizing_slippage: value.uses_quote_minimizing_slippage,
+            probe_amount: value.probe_amount,
+            applied_compute_unit_score: value.applied_compute_unit_score.clone(),
+            platform_fee: value
+                .platform_fee
+                .as_ref()
+                .map(|platform_fee| BinaryPlatformFee {
+                    amount: platform_fee.amount,
+                    fee_bps: platform_fee.fee_bps,
+                }),
+            price_impact_pct: value.price_impact_pct.serialize(),
+            route_plan: value
+                .route_plan
+                .iter()
+                .map(|step| BinaryRoutePlanStep {
+                    swap_info: BinarySwapInfo {
+                        amm_key: step.swap_info.amm_key,
+                        label: step.swap_info.label.clone(),
+                        input_mint: step.swap_info.input_mint,
+                        output_mint: step.swap_info.output_mint,
+                        in_amount: step.swap_info.in_amount,
+                        out_amount: step.swap_info.out_amount,
+                        fee_amount: step.swap_info.fee_amount,
+                        fee_mint: step.swap_info.fee_mint,
+                        extra: serde_json::to_string(&step.swap_info.extra).unwrap_or_default(),
+                    },
+                    percent: step.percent,
+                })
+                .collect(),
+            context_slot: value.context_slot,
+            time_taken: value.time_taken,
+        }
+    }
+}
+
+impl From<BinaryQuoteResponse> for QuoteResponse {
+    fn from(value: BinaryQuoteResponse) -> Self {
+        Self {
+            input_mint: value.input_mint,
+            in_amount: value.in_amount,
+            output_mint: value.output_mint,
+            out_amount: value.out_amount,
+            other_amount_threshold: value.other_amount_threshold,
+            swap_mode: value.swap_mode,
+            slippage_bps: value.slippage_bps,
+            computed_auto_slippage: value.computed_auto_slippage,
+            uses_quote_minimizing_slippage: value.uses_quote_minimizing_slippage,
+            probe_amount: value.probe_amount,
+            applied_compute_unit_score: value.applied_compute_unit_score,
+            platform_fee: value.platform_fee.map(|platform_fee| PlatformFee {
+                amount: platform_fee.amount,
+                fee_bps: platform_fee.fee_bps,
+            }),
+            price_impact_pct: Decimal::deserialize(value.price_impact_pct),
+            route_plan: value
+                .route_plan
+                .into_iter()
+                .map(|step| RoutePlanStep {
+                    swap_info: SwapInfo {
+                        amm_key: step.swap_info.amm_key,
+                        label: step.swap_info.label,
+                        input_mint: step.swap_info.input_mint,
+                        output_mint: step.swap_info.output_mint,
+                        in_amount: step.swap_info.in_amount,
+                        out_amount: step.swap_info.out_amount,
+                        fee_amount: step.swap_info.fee_amount,
+                        fee_mint: step.swap_info.fee_mint,
+                        extra: serde_json::from_str(&step.swap_info.extra).unwrap_or_default(),
+                    },
+                    percent: step.percent,
+                })
+                .collect(),
+            context_slot: value.context_slot,
+            time_taken: value.time_taken,
+        }
+    }
+}
+
+impl QuoteResponse {
+    pub fn to_bytes(&self) -> Result<Vec<u8>, bincode::Error> {
+        bincode::serialize(&BinaryQuoteResponse::from(self))
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, bincode::Error> {
+        bincode::deserialize::<BinaryQuoteResponse>(bytes).map(Into::into)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+
+    use super::*;
+
+    fn quote(fixture: &str) -> QuoteResponse {
+        serde_json::from_str(fixture).unwrap()
+    }
+
+    fn round_trip(quote: &QuoteResponse) -> Value {
+        let bytes = quote.to_bytes().unwrap();
+        serde_json::to_value(QuoteResponse::from_bytes(&bytes).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn round_trips_a_plain_quote() {
+        let quote = quote(include_str!("../tests/fixtures/quote_exact_in.json"));
+        assert_eq!(round_trip(&quote), serde_json::to_value(&quote).unwrap());
+    }
+
+    #[test]
+    fn round_trips_every_optional_field() {
+        let mut quote = quote(include_str!(
+            "../tests/fixtures/quote_exact_in_platform_fee.json"
+        ));
+        quote.computed_auto_slippage = Some(30);
+        quote.uses_quote_minimizing_slippage = Some(true);
+        quote.probe_amount = Some(1_000_000);
+        quote.applied_compute_unit_score = Some(AppliedComputeUnitScore {
+            max_penalty_bps: Some(12.5),
+        });
+        let swap_info = &mut quote.route_plan[0].swap_info;
+        swap_info.fee_amount = Some(u64::MAX);
+        swap_info.fee_mint = Some(Pubkey::new_unique());
+        swap_info.extra.insert(
+            String::from("ammBump"),
+            json!({ "nested": [1, "two", null] }),
+        );
+        assert_eq!(round_trip(&quote), serde_json::to_value(&quote).unwrap());
+    }
+
+    #[test]
+    fn rejects_truncated_bytes() {
+        let quote = quote(include_str!("../tests/fixtures/quote_exact_in.json"));
+        let bytes = quote.to_bytes().unwrap();
+        assert!(QuoteResponse::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+}
@@ -0,0 +1,20 @@
+//! Latency ranking of the endpoints serving the API, see [`crate::JupiterSwapApiClient::with_endpoints`]
+//!
+
+use std::time::Duration;
+
+/// Endpoints not answering a probe within this time are considered unhealthy
+pub const ENDPOINT_PROBE_TIMEOUT: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointHealth {
+    pub base_path: String,
+    /// Round trip time of the last probe, `None` when it failed, timed out or got a server error
+    pub latency: Option<Duration>,
+}
+
+/// Healthy endpoints by increasing latency, followed by unhealthy ones in their original order
+pub(crate) fn rank(mut endpoints: Vec<EndpointHealth>) -> Vec<EndpointHealth> {
+    endpoints.sort_by_key(|endpoint| (endpoint.latency.is_none(), endpoint.latency));
+    endpoints
+}
@@ -0,0 +1,136 @@
+//! Captured quote request and response pairs, for replaying production traffic
+//!
+
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter},
+    path::Path,
+    sync::Arc,
+};
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpListener,
+    task::JoinHandle,
+};
+
+use crate::quote::{QuoteRequest, QuoteResponse};
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct QuoteExchange {
+    pub request: QuoteRequest,
+    pub response: QuoteResponse,
+}
+
+impl QuoteExchange {
+    /// Write the exchange as JSON to `path`
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        Ok(serde_json::from_reader(reader)?)
+    }
+}
+
+/// Local HTTP server replaying a captured [`QuoteExchange`]: every `/quote` request is answered with
+/// the captured response and any other path with a 404. Point a client at [`Self::base_url`] to
+/// replay production traffic offline. The server stops when the transport is dropped.
+pub struct MockTransport {
+    base_url: String,
+    server: JoinHandle<()>,
+}
+
+impl MockTransport {
+    /// Start serving `exchange` on a free local port. Must be called within a tokio runtime.
+    pub async fn from_exchange(exchange: &QuoteExchange) -> Result<Self> {
+        let body = Arc::new(serde_json::to_string(&exchange.response)?);
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let base_url = format!("http://{}", listener.local_addr()?);
+        let server = tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut request = Vec::new();
+                    let mut buffer = [0; 4096];
+                    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+                        match stream.read(&mut buffer).await {
+                            Ok(0) | Err(_) => return,
+                            Ok(read) => request.extend_from_slice(&buffer[..read]),
+                        }
+                    }
+                    let request = String::from_utf8_lossy(&request);
+                    let path = request.split_whitespace().nth(1).unwrap_or_default();
+                    let (status, body) = match path.split('?').next() {
+                        Some("/quote") => ("200 OK", body.as_str()),
+                        _ => ("404 Not Found", r#"{"error":"not captured"}"#),
+                    };
+                    let response = format!(
+                        "HTTP/1.1 {status}\r\ncontent-type: application/json\r\n\
+                         content-length: {}\r\nconnection: close\r\n\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        Ok(Self { base_url, server })
+    }
+
+    /// Base URL to create a [`crate::JupiterSwapApiClient`] with
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+}
+
+impl Drop for MockTransport {
+    fn drop(&mut self) {
+        self.server.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::{tests::quote_json, JupiterSwapApiClient};
+
+    #[tokio::test]
+    async fn saved_exchange_replays_through_mock_transport() {
+        let response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        let exchange = QuoteExchange {
+            request: QuoteRequest {
+                input_mint: response.input_mint,
+                output_mint: response.output_mint,
+                amount: response.in_amount,
+                slippage_bps: response.slippage_bps,
+                ..QuoteRequest::default()
+            },
+            response,
+        };
+        let path = std::env::temp_dir().join(format!("exchange-{}.json", Pubkey::new_unique()));
+        exchange.save(&path).unwrap();
+        let loaded = QuoteExchange::load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let transport = MockTransport::from_exchange(&loaded).await.unwrap();
+        let client =
+            JupiterSwapApiClient::new(transport.base_url().to_string(), String::new()).unwrap();
+        let replayed = client.quote(&loaded.request).await.unwrap();
+
+        assert_eq!(
+            serde_json::to_value(&replayed).unwrap(),
+            serde_json::to_value(&exchange.response).unwrap()
+        );
+        assert_eq!(
+            serde_json::to_value(&loaded.request).unwrap(),
+            serde_json::to_value(&exchange.request).unwrap()
+        );
+    }
+}
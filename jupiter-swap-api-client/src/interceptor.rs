@@ -0,0 +1,23 @@
+//! Hooks for mutating outgoing requests and incoming responses
+//!
+
+/// Applied to every request right before it is sent, e.g. to add headers, sign or log
+pub trait RequestInterceptor: Send + Sync {
+    fn on_request(&self, request: &mut reqwest::Request);
+}
+
+/// Applied to the raw JSON of quote and swap responses before typed deserialization,
+/// e.g. to patch a known API quirk
+pub trait ResponseTransform: Send + Sync {
+    fn transform(&self, value: serde_json::Value) -> serde_json::Value;
+}
+
+/// Leaves responses untouched, the behavior of a client without a transform
+#[derive(Debug, Default, Clone, Copy)]
+pub struct IdentityTransform;
+
+impl ResponseTransform for IdentityTransform {
+    fn transform(&self, value: serde_json::Value) -> serde_json::Value {
+        value
+    }
+}
@@ -0,0 +1,165 @@
+//! Jito bundles of swap transactions, executed atomically and in order
+//!
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use solana_sdk::{packet::PACKET_DATA_SIZE, transaction::VersionedTransaction};
+
+use crate::{
+    swap::{transaction_tip_lamports, SwapResponse, JITO_TIP_ACCOUNTS},
+    ClientError,
+};
+
+/// Maximum number of transactions Jito accepts in a bundle
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+/// Transactions to submit together through Jito's `sendBundle`, in execution order
+#[derive(Debug, Clone, Default)]
+pub struct JitoBundle {
+    transactions: Vec<VersionedTransaction>,
+}
+
+impl JitoBundle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append the transaction of `swap_response`, which still has to be signed, see [`Self::push_transaction`]
+    pub fn push_swap(&mut self, swap_response: &SwapResponse) -> Result<(), ClientError> {
+        self.transactions.push(swap_response.transaction()?);
+        Ok(())
+    }
+
+    /// Append a transaction, such as one tipping a [`JITO_TIP_ACCOUNTS`] account
+    pub fn push_transaction(&mut self, transaction: VersionedTransaction) {
+        self.transactions.push(transaction);
+    }
+
+    /// Mutable access to the transactions, e.g. to sign swap transactions in place before submission
+    pub fn transactions_mut(&mut self) -> &mut [VersionedTransaction] {
+        &mut self.transactions
+    }
+
+    pub fn transactions(&self) -> &[VersionedTransaction] {
+        &self.transactions
+    }
+
+    /// Check what Jito would reject: an empty bundle or one with more than [`MAX_BUNDLE_TRANSACTIONS`],
+    /// a transaction larger than [`PACKET_DATA_SIZE`] or missing a valid signature, and a bundle without
+    /// any transfer to [`JITO_TIP_ACCOUNTS`]. Tips loaded through address lookup tables are not found.
+    pub fn validate(&self) -> Result<(), ClientError> {
+        let invalid = |reason: String| Err(ClientError::InvalidBundle(reason));
+        if self.transactions.is_empty() || self.transactions.len() > MAX_BUNDLE_TRANSACTIONS {
+            return invalid(format!(
+                "a bundle holds 1 to {MAX_BUNDLE_TRANSACTIONS} transactions, got {}",
+                self.transactions.len()
+            ));
+        }
+        for (index, transaction) in self.transactions.iter().enumerate() {
+            let size = bincode::serialized_size(transaction)?;
+            if size > PACKET_DATA_SIZE as u64 {
+                return invalid(format!(
+                    "transaction {index} is {size} bytes, over the {PACKET_DATA_SIZE} bytes limit"
+                ));
+            }
+            let required_signatures =
+                usize::from(transaction.message.header().num_required_signatures);
+            if transaction.signatures.len() != required_signatures
+                || !transaction
+                    .verify_with_results()
+                    .into_iter()
+                    .all(|valid| valid)
+            {
+                return invalid(format!("transaction {index} is not fully signed"));
+            }
+        }
+        if !self
+            .transactions
+            .iter()
+            .any(|transaction| transaction_tip_lamports(transaction, &JITO_TIP_ACCOUNTS).is_some())
+        {
+            return invalid("no transaction tips a Jito tip account".to_string());
+        }
+        Ok(())
+    }
+
+    /// Validate the bundle and encode its transactions as the base64 strings `sendBundle` expects,
+    /// to be sent with the `{"encoding": "base64"}` option
+    pub fn to_base64(&self) -> Result<Vec<String>, ClientError> {
+        self.validate()?;
+        self.transactions
+            .iter()
+            .map(|transaction| Ok(STANDARD.encode(bincode::serialize(transaction)?)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::{
+        hash::Hash,
+        message::{Message, VersionedMessage},
+        pubkey::Pubkey,
+        signature::Keypair,
+        signer::Signer,
+        system_instruction::transfer,
+    };
+
+    use super::*;
+
+    /// A signed transaction transferring `lamports` from `payer` to `recipient`
+    fn transfer_transaction(
+        payer: &Keypair,
+        recipient: &Pubkey,
+        lamports: u64,
+    ) -> VersionedTransaction {
+        let message = Message::new_with_blockhash(
+            &[transfer(&payer.pubkey(), recipient, lamports)],
+            Some(&payer.pubkey()),
+            &Hash::new_unique(),
+        );
+        VersionedTransaction::try_new(VersionedMessage::Legacy(message), &[payer]).unwrap()
+    }
+
+    #[test]
+    fn bundle_with_a_trailing_tip_transaction_is_valid() {
+        let payer = Keypair::new();
+        let swap = transfer_transaction(&payer, &Pubkey::new_unique(), 1_000_000);
+        let tip = transfer_transaction(&payer, &JITO_TIP_ACCOUNTS[0], 10_000);
+
+        let mut bundle = JitoBundle::new();
+        bundle.push_transaction(swap.clone());
+        assert!(matches!(
+            bundle.validate(),
+            Err(ClientError::InvalidBundle(_))
+        ));
+
+        bundle.push_transaction(tip.clone());
+        bundle.validate().unwrap();
+        assert_eq!(
+            transaction_tip_lamports(&bundle.transactions()[1], &JITO_TIP_ACCOUNTS),
+            Some(10_000)
+        );
+        assert_eq!(
+            transaction_tip_lamports(&bundle.transactions()[0], &JITO_TIP_ACCOUNTS),
+            None
+        );
+        assert_eq!(
+            bundle.to_base64().unwrap(),
+            [&swap, &tip]
+                .map(|transaction| STANDARD.encode(bincode::serialize(transaction).unwrap()))
+        );
+    }
+
+    #[test]
+    fn bundle_with_an_unsigned_transaction_is_invalid() {
+        let payer = Keypair::new();
+        let mut bundle = JitoBundle::new();
+        bundle.push_transaction(transfer_transaction(&payer, &Pubkey::new_unique(), 1));
+        bundle.push_transaction(transfer_transaction(&payer, &JITO_TIP_ACCOUNTS[0], 10_000));
+        bundle.transactions_mut()[0].signatures[0] = Default::default();
+        assert!(matches!(
+            bundle.to_base64(),
+            Err(ClientError::InvalidBundle(_))
+        ));
+    }
+}
@@ -1,21 +1,195 @@
-use std::collections::HashMap;
+use std::{
+    collections::{HashMap, HashSet},
+    future::Future,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, RwLock,
+    },
+    time::{Duration, Instant},
+};
 
-use quote::{InternalQuoteRequest, QuoteRequest, QuoteResponse};
-use reqwest::{Client, header, Response};
-use serde::de::DeserializeOwned;
-use swap::{SwapInstructionsResponse, SwapInstructionsResponseInternal, SwapRequest, SwapResponse};
+use amounts::base_to_ui_amount;
+use anyhow::Result;
+use endpoint::{EndpointHealth, ENDPOINT_PROBE_TIMEOUT};
+use futures::future::{join_all, try_join_all};
+use interceptor::{RequestInterceptor, ResponseTransform};
+use quote::{
+    BothModesQuote, CandidateQuote, CandidateQuotes, InternalQuoteRequest,
+    MinimizeSlippageComparison, QuoteDiff, QuoteRequest, QuoteResponse, SwapMode, TimedQuote,
+};
+use rate_limit::RateLimitStatus;
+use reqwest::{header, Client, Method, RequestBuilder, Response};
+use route_plan_with_metadata::RoutePlanWithMetadataExt;
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_helpers::safe_u64;
+use serde_json::Value;
+use solana_sdk::{
+    pubkey::Pubkey,
+    signature::Signature,
+    signer::{Signer, SignerError},
+    transaction::VersionedTransaction,
+};
+use swap::{
+    SwapInstructionsResponse, SwapInstructionsResponseInternal, SwapRequest, SwapResponse,
+    UiSimulationError,
+};
 use thiserror::Error;
-use anyhow::{ Result };
+use token::TokenInfo;
+use transaction_config::TransactionConfig;
+pub mod amounts;
+#[cfg(feature = "bincode")]
+mod binary;
+pub mod endpoint;
+pub mod exchange;
+pub mod interceptor;
+pub mod jito;
 pub mod quote;
+pub mod rate_limit;
+pub mod referral;
 pub mod route_plan_with_metadata;
 pub mod serde_helpers;
+pub mod stream;
 pub mod swap;
+pub mod token;
 pub mod transaction_config;
 
+pub const DEFAULT_TOKEN_API_BASE_PATH: &str = "https://api.jup.ag/tokens/v1";
+
+/// Header set by [`JupiterSwapApiClient::with_api_version_header`]
+pub const API_VERSION_HEADER: &str = "x-api-version";
+
+/// Cloning is cheap: clones share the underlying HTTP connection pool and caches,
+/// so a single client can be cloned freely across tasks.
 #[derive(Clone)]
 pub struct JupiterSwapApiClient {
     pub base_path: String,
-    client: Client
+    pub token_api_base_path: String,
+    client: Client,
+    auth_key: String,
+    config: Arc<ClientConfig>,
+    token_decimals_cache: Arc<RwLock<HashMap<Pubkey, u8>>>,
+    last_rate_limit_status: Arc<RwLock<Option<RateLimitStatus>>>,
+    latest_context_slot: Arc<AtomicU64>,
+    endpoint_ranking: Arc<RwLock<Vec<EndpointHealth>>>,
+}
+
+#[derive(Clone, Default)]
+struct ClientConfig {
+    dry_run: bool,
+    interceptors: Vec<Arc<dyn RequestInterceptor>>,
+    response_transform: Option<Arc<dyn ResponseTransform>>,
+    forbidden_intermediate_mints: HashSet<Pubkey>,
+    default_slippage_bps: Option<u16>,
+    referral_program_id: Option<Pubkey>,
+    api_version: Option<header::HeaderValue>,
+    fail_on_simulation_error: bool,
+    param_case: ParamCase,
+    legacy_transaction_max_route_steps: Option<usize>,
+    endpoints: Vec<String>,
+}
+
+/// Versioned path layouts of the swap API, see [`JupiterSwapApiClient::with_api_version`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ApiVersion {
+    /// `/v6`, as served by `quote-api.jup.ag`
+    V6,
+    /// `/swap/v1`, as served by `api.jup.ag` and `lite-api.jup.ag`
+    SwapV1,
+}
+
+impl ApiVersion {
+    pub fn path(self) -> &'static str {
+        match self {
+            ApiVersion::V6 => "/v6",
+            ApiVersion::SwapV1 => "/swap/v1",
+        }
+    }
+}
+
+/// Naming of the fields in outgoing query strings and JSON bodies. The Jupiter API expects camelCase,
+/// some self-hosted forks snake_case.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ParamCase {
+    #[default]
+    Camel,
+    Snake,
+}
+
+fn camel_to_snake_case(name: &str) -> String {
+    let mut snake_case = String::with_capacity(name.len() + 4);
+    for c in name.chars() {
+        if c.is_ascii_uppercase() {
+            snake_case.push('_');
+            snake_case.push(c.to_ascii_lowercase());
+        } else {
+            snake_case.push(c);
+        }
+    }
+    snake_case
+}
+
+/// Rename the top-level keys of `value` from camelCase to snake_case. Nested objects are left
+/// untouched, as the embedded `quoteResponse` must be sent back exactly as the API returned it,
+/// including AMM specific keys captured in [`quote::SwapInfo::extra`].
+fn rename_keys_to_snake_case(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (camel_to_snake_case(&key), value))
+                .collect(),
+        ),
+        value => value,
+    }
+}
+
+/// Which HTTP redirects the client follows. Redirects are followed with every default header,
+/// including the `x-api-key`, so following them to another origin may leak the key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RedirectPolicy {
+    /// Never follow redirects, returning the redirect response as a failed request
+    None,
+    /// Follow up to 10 redirects as long as they stay on the same origin: scheme, host and port
+    #[default]
+    SameHost,
+    /// Follow up to this many redirects to any host, failing the request on the next one
+    Limited(usize),
+}
+
+impl RedirectPolicy {
+    const MAX_SAME_HOST_REDIRECTS: usize = 10;
+
+    fn to_reqwest(self) -> reqwest::redirect::Policy {
+        match self {
+            Self::None => reqwest::redirect::Policy::none(),
+            Self::SameHost => reqwest::redirect::Policy::custom(|attempt| {
+                let same_origin = attempt
+                    .previous()
+                    .first()
+                    .is_some_and(|original| is_same_origin(original, attempt.url()));
+                if !same_origin || attempt.previous().len() > Self::MAX_SAME_HOST_REDIRECTS {
+                    attempt.stop()
+                } else {
+                    attempt.follow()
+                }
+            }),
+            // `Policy::limited` counts the original URL too, following one redirect less
+            Self::Limited(max_redirects) => reqwest::redirect::Policy::custom(move |attempt| {
+                if attempt.previous().len() > max_redirects {
+                    attempt.error("too many redirects")
+                } else {
+                    attempt.follow()
+                }
+            }),
+        }
+    }
+}
+
+fn is_same_origin(original: &reqwest::Url, target: &reqwest::Url) -> bool {
+    original.scheme() == target.scheme()
+        && original.host_str() == target.host_str()
+        && original.port_or_known_default() == target.port_or_known_default()
 }
 
 #[derive(Debug, Error)]
@@ -27,64 +201,796 @@ pub enum ClientError {
     },
     #[error("Failed to deserialize response: {0}")]
     DeserializationError(#[from] reqwest::Error),
+    #[error("Failed to deserialize response: {0}")]
+    JsonDeserializationError(#[from] serde_json::Error),
+    #[error("Response has fields unknown to the client: {}", .0.join(", "))]
+    UnknownFields(Vec<String>),
+    #[error("Token decimals {0} exceed the 28 decimal places supported")]
+    UnsupportedDecimals(u8),
+    #[error("None of the candidate output mints could be quoted")]
+    NoCandidateQuote,
+    #[error("Failed to decode transaction: {0}")]
+    TransactionDecodeError(#[from] bincode::Error),
+    #[error("Invalid Jito bundle: {0}")]
+    InvalidBundle(String),
+    #[error("Transaction does not match quote: {0}")]
+    TransactionMismatch(String),
+    #[error("Transaction cannot be verified against quote: {0}")]
+    UnverifiableTransaction(String),
+    #[error("Failed to sign transaction: {0}")]
+    SigningFailed(#[from] SignerError),
+    #[error("Failed to send transaction: {0}")]
+    SendFailed(Box<dyn std::error::Error + Send + Sync>),
+    #[error("Swap simulation failed with {}: {}", .0.error_code, .0.error)]
+    SimulationFailed(UiSimulationError),
+    #[error("Invalid request: {0}")]
+    InvalidRequest(String),
+    #[error("No route found")]
+    NoRouteFound,
+    #[error("Quote from slot {context_slot} is stale at slot {current_slot}")]
+    StaleQuote {
+        context_slot: u64,
+        current_slot: u64,
+    },
+    #[error("Route passes through forbidden intermediate mint {0}")]
+    ForbiddenIntermediateMint(Pubkey),
+    #[error("Dry run, request not sent: {method} {url}")]
+    DryRun {
+        method: Method,
+        url: String,
+        body: Option<String>,
+    },
+}
+
+impl ClientError {
+    /// Whether retrying the same request may succeed: timeouts, connection failures,
+    /// rate limiting and server errors
+    pub fn is_transient(&self) -> bool {
+        match self {
+            ClientError::RequestFailed { status, .. } => {
+                *status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+            }
+            ClientError::DeserializationError(error) => error.is_timeout() || error.is_connect(),
+            _ => false,
+        }
+    }
+
+    /// The JSON error body of a [`ClientError::RequestFailed`], when the API sent one
+    pub fn api_error_body(&self) -> Option<ApiErrorBody> {
+        match self {
+            ClientError::RequestFailed { body, .. } => serde_json::from_str(body).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// Error body sent by the API along with a non-success status
+#[derive(Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ApiErrorBody {
+    pub error: Option<String>,
+    pub error_code: Option<String>,
+}
+
+/// `errorCode`s retried by [`JupiterSwapApiClient::quote_within`]: routing failures that depend on the
+/// liquidity at the time of the quote and may clear up, unlike errors such as a non-tradable token
+pub const DEFAULT_RETRYABLE_ERROR_CODES: &[&str] = &[
+    "COULD_NOT_FIND_ANY_ROUTE",
+    "ROUTE_PLAN_DOES_NOT_CONSUME_ALL_THE_AMOUNT",
+];
+
+/// Delay before the first retry of [`JupiterSwapApiClient::quote_within_retrying`],
+/// doubled after each attempt
+const QUOTE_WITHIN_INITIAL_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+/// Join a base URL, which may carry a path prefix such as `/jupiter/v6`, with an endpoint path,
+/// without doubling or dropping the separating slash
+pub fn join_url(base_url: &str, path: &str) -> String {
+    format!(
+        "{}/{}",
+        base_url.trim_end_matches('/'),
+        path.trim_start_matches('/')
+    )
+}
+
+/// Render a ready-to-run curl command; the API key is never included, only a placeholder
+pub(crate) fn curl_command(method: &str, url: &str, body: Option<&str>) -> String {
+    fn shell_quote(value: &str) -> String {
+        format!("'{}'", value.replace('\'', "'\\''"))
+    }
+
+    let mut command = format!(
+        "curl -X {method} {} -H {} -H {}",
+        shell_quote(url),
+        shell_quote("x-api-key: <redacted>"),
+        shell_quote("Content-Type: application/json"),
+    );
+    if let Some(body) = body {
+        command.push_str(&format!(" -d {}", shell_quote(body)));
+    }
+    command
+}
+
+fn check_swap_request(
+    swap_request: &SwapRequest,
+    legacy_transaction_max_route_steps: Option<usize>,
+) -> Result<(), ClientError> {
+    let quote_response = &swap_request.quote_response;
+    if let Err(reason) = swap_request
+        .config
+        .validate_for_mints(&quote_response.input_mint, &quote_response.output_mint)
+    {
+        log::warn!("{reason}");
+    }
+    let route_steps = quote_response.route_plan.len();
+    let too_many_steps = legacy_transaction_max_route_steps
+        .is_some_and(|max_route_steps| route_steps > max_route_steps);
+    if swap_request.config.as_legacy_transaction && too_many_steps {
+        return Err(ClientError::InvalidRequest(format!(
+            "as_legacy_transaction is set but the quote has {route_steps} route steps, \
+             request the quote with as_legacy_transaction too or the transaction may be too large"
+        )));
+    }
+    Ok(())
+}
+
+/// Non-JSON bodies, typically HTML error pages from a gateway or CDN, are cut to this many characters
+const NON_JSON_BODY_SNIPPET_CHARS: usize = 512;
+
+fn is_json(response: &Response) -> bool {
+    response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|content_type| content_type.to_str().ok())
+        .is_some_and(|content_type| content_type.contains("json"))
+}
+
+async fn request_failed(response: Response) -> ClientError {
+    let status = response.status();
+    let is_json = is_json(&response);
+    let mut body = response.text().await.unwrap_or_default();
+    if !is_json {
+        if let Some((index, _)) = body.char_indices().nth(NON_JSON_BODY_SNIPPET_CHARS) {
+            body.truncate(index);
+            body.push_str("...");
+        }
+    }
+    ClientError::RequestFailed { status, body }
 }
 
 async fn check_is_success(response: Response) -> Result<Response, ClientError> {
     if !response.status().is_success() {
-        let status = response.status();
-        let body = response.text().await.unwrap_or_default();
-        return Err(ClientError::RequestFailed { status, body });
+        return Err(request_failed(response).await);
     }
     Ok(response)
 }
 
+/// A successful response that is not JSON, such as an HTML page served by a proxy, is reported as
+/// [`ClientError::RequestFailed`] with the start of its body rather than as a deserialization error
 async fn check_status_code_and_deserialize<T: DeserializeOwned>(
     response: Response,
 ) -> Result<T, ClientError> {
     let response = check_is_success(response).await?;
-    response
-        .json::<T>()
-        .await
-        .map_err(ClientError::DeserializationError)
+    if response.headers().contains_key(header::CONTENT_TYPE) && !is_json(&response) {
+        return Err(request_failed(response).await);
+    }
+    let body = response.bytes().await?;
+    serde_json::from_slice(&body).map_err(|error| {
+        let value = serde_json::from_slice(&body).unwrap_or_default();
+        ClientError::JsonDeserializationError(safe_u64::with_amount_path(error, &value))
+    })
+}
+
+fn build_http_client(auth_key: &str, redirect_policy: RedirectPolicy) -> Result<Client> {
+    Ok(Client::builder()
+        .pool_max_idle_per_host(10)
+        .tcp_keepalive(std::time::Duration::from_secs(35))
+        .redirect(redirect_policy.to_reqwest())
+        .default_headers(header::HeaderMap::from_iter([
+            (
+                header::HeaderName::from_static("x-api-key"),
+                header::HeaderValue::from_str(auth_key)?,
+            ),
+            (
+                header::CONTENT_TYPE,
+                header::HeaderValue::from_static("application/json"),
+            ),
+        ]))
+        .build()?)
 }
 
 impl JupiterSwapApiClient {
     pub fn new(base_path: String, auth_key: String) -> Result<Self> {
-        let client = Client::builder()
-            .pool_max_idle_per_host(10)
-            .tcp_keepalive(std::time::Duration::from_secs(35))
-            .default_headers(
-                header::HeaderMap::from_iter([
-                    (
-                        header::HeaderName::from_static("x-api-key"),
-                        header::HeaderValue::from_str(&auth_key)?
-                    ),
-                    (
-                        header::CONTENT_TYPE,
-                        header::HeaderValue::from_static("application/json"),
-                    )
-                ])
-            )
-            .build()?;
-        Ok(
-            Self {
-                base_path,
-                client
+        let client = build_http_client(&auth_key, RedirectPolicy::default())?;
+        Ok(Self {
+            base_path,
+            token_api_base_path: DEFAULT_TOKEN_API_BASE_PATH.to_string(),
+            client,
+            auth_key,
+            config: Arc::default(),
+            token_decimals_cache: Arc::default(),
+            last_rate_limit_status: Arc::default(),
+            latest_context_slot: Arc::default(),
+            endpoint_ranking: Arc::default(),
+        })
+    }
+
+    /// Point `base_path` at the path layout of `version`, replacing a version path `base_path` already
+    /// ends with and keeping any prefix before it: `https://example.com/jupiter/v6` becomes
+    /// `https://example.com/jupiter/swap/v1` for [`ApiVersion::SwapV1`], while `https://api.jup.ag`
+    /// becomes `https://api.jup.ag/swap/v1`. Endpoints given to [`Self::with_endpoints`] are unchanged.
+    pub fn with_api_version(mut self, version: ApiVersion) -> Self {
+        let base_path = self.base_path.trim_end_matches('/');
+        let prefix = [ApiVersion::V6, ApiVersion::SwapV1]
+            .into_iter()
+            .find_map(|known| base_path.strip_suffix(known.path()))
+            .unwrap_or(base_path);
+        self.base_path = format!("{prefix}{}", version.path());
+        self
+    }
+
+    /// Rebuilds the underlying HTTP client, so the connection pool is no longer shared with
+    /// clients cloned before this call
+    pub fn with_redirect_policy(mut self, redirect_policy: RedirectPolicy) -> Result<Self> {
+        self.client = build_http_client(&self.auth_key, redirect_policy)?;
+        Ok(self)
+    }
+
+    /// In dry-run mode, requests are built and serialized as usual but never sent:
+    /// every call returns [`ClientError::DryRun`] carrying the request that would have been sent.
+    pub fn with_dry_run(mut self, dry_run: bool) -> Self {
+        Arc::make_mut(&mut self.config).dry_run = dry_run;
+        self
+    }
+
+    /// Interceptors run in registration order on every outgoing request
+    pub fn with_interceptor(mut self, interceptor: impl RequestInterceptor + 'static) -> Self {
+        Arc::make_mut(&mut self.config)
+            .interceptors
+            .push(Arc::new(interceptor));
+        self
+    }
+
+    /// Replace the transform applied to quote and swap responses before deserialization
+    pub fn with_response_transform(
+        mut self,
+        response_transform: impl ResponseTransform + 'static,
+    ) -> Self {
+        Arc::make_mut(&mut self.config).response_transform = Some(Arc::new(response_transform));
+        self
+    }
+
+    /// Quotes routing through any of these mints as an intermediate token fail with
+    /// [`ClientError::ForbiddenIntermediateMint`]. The API cannot exclude mints, so this is checked client-side.
+    pub fn with_forbidden_intermediate_mints(
+        mut self,
+        mints: impl IntoIterator<Item = Pubkey>,
+    ) -> Self {
+        Arc::make_mut(&mut self.config)
+            .forbidden_intermediate_mints
+            .extend(mints);
+        self
+    }
+
+    /// Slippage applied to quote requests leaving `slippage_bps` at 0 without `auto_slippage`.
+    /// An explicit 0 cannot be told apart from an unset value, so it is replaced as well.
+    pub fn with_default_slippage_bps(mut self, slippage_bps: u16) -> Self {
+        Arc::make_mut(&mut self.config).default_slippage_bps = Some(slippage_bps);
+        self
+    }
+
+    /// Send `version` in the [`API_VERSION_HEADER`] header of every request. The public Jupiter API
+    /// carries its version in the path, see [`Self::with_api_version`], and knows no header versions,
+    /// so this is meant for gateways and self-hosted deployments that negotiate the response shape by
+    /// header.
+    pub fn with_api_version_header(mut self, version: String) -> Result<Self> {
+        Arc::make_mut(&mut self.config).api_version = Some(header::HeaderValue::try_from(version)?);
+        Ok(self)
+    }
+
+    /// Name the top-level fields of outgoing requests in `param_case`, for self-hosted forks not
+    /// expecting camelCase. Nested objects, such as the quote embedded in swap requests, and extra quote
+    /// and swap arguments given as a map are sent with their names unchanged.
+    pub fn with_param_case(mut self, param_case: ParamCase) -> Self {
+        Arc::make_mut(&mut self.config).param_case = param_case;
+        self
+    }
+
+    fn query_in_param_case<T: Serialize>(
+        &self,
+        request_builder: RequestBuilder,
+        params: &T,
+    ) -> Result<RequestBuilder, ClientError> {
+        Ok(match self.config.param_case {
+            ParamCase::Camel => request_builder.query(params),
+            ParamCase::Snake => {
+                let mut params = rename_keys_to_snake_case(serde_json::to_value(params)?);
+                // Absent optional params would otherwise be sent empty
+                if let Value::Object(fields) = &mut params {
+                    fields.retain(|_, value| !value.is_null());
+                }
+                request_builder.query(&params)
+            }
+        })
+    }
+
+    fn json_in_param_case<T: Serialize>(
+        &self,
+        request_builder: RequestBuilder,
+        body: &T,
+    ) -> Result<RequestBuilder, ClientError> {
+        Ok(match self.config.param_case {
+            ParamCase::Camel => request_builder.json(body),
+            ParamCase::Snake => {
+                request_builder.json(&rename_keys_to_snake_case(serde_json::to_value(body)?))
+            }
+        })
+    }
+
+    /// Swap responses reporting a `simulationError`, which the API returns alongside a transaction when
+    /// the simulation run for `dynamic_compute_unit_limit` fails, become [`ClientError::SimulationFailed`]
+    pub fn with_fail_on_simulation_error(mut self, fail_on_simulation_error: bool) -> Self {
+        Arc::make_mut(&mut self.config).fail_on_simulation_error = fail_on_simulation_error;
+        self
+    }
+
+    /// Swap requests with `as_legacy_transaction` set whose quote has more than `max_route_steps` route
+    /// steps fail with [`ClientError::InvalidRequest`], as longer routes likely come from a quote requested
+    /// without `as_legacy_transaction` and may not fit in a legacy transaction. The route size only hints
+    /// at the transaction size, so this check is off by default.
+    pub fn with_legacy_transaction_max_route_steps(mut self, max_route_steps: usize) -> Self {
+        Arc::make_mut(&mut self.config).legacy_transaction_max_route_steps = Some(max_route_steps);
+        self
+    }
+
+    fn check_simulation_error(
+        &self,
+        simulation_error: &Option<UiSimulationError>,
+    ) -> Result<(), ClientError> {
+        match simulation_error {
+            Some(simulation_error) if self.config.fail_on_simulation_error => {
+                Err(ClientError::SimulationFailed(simulation_error.clone()))
             }
+            _ => Ok(()),
+        }
+    }
+
+    /// Referral program used by [`Self::referral_fee_account`], instead of [`referral::REFERRAL_PROGRAM_ID`]
+    pub fn with_referral_program_id(mut self, referral_program_id: Pubkey) -> Self {
+        Arc::make_mut(&mut self.config).referral_program_id = Some(referral_program_id);
+        self
+    }
+
+    /// Derive the fee token account of `referral_account` for `mint` under the configured referral program
+    pub fn referral_fee_account(&self, referral_account: Pubkey, mint: Pubkey) -> Pubkey {
+        referral::derive_referral_fee_account_with_program_id(
+            referral_account,
+            mint,
+            self.config
+                .referral_program_id
+                .as_ref()
+                .unwrap_or(&referral::REFERRAL_PROGRAM_ID),
         )
     }
 
+    async fn send(&self, request_builder: RequestBuilder) -> Result<Response, ClientError> {
+        let mut request = request_builder.build()?;
+        if let Some(api_version) = &self.config.api_version {
+            request
+                .headers_mut()
+                .insert(API_VERSION_HEADER, api_version.clone());
+        }
+        for interceptor in &self.config.interceptors {
+            interceptor.on_request(&mut request);
+        }
+        if self.config.dry_run {
+            return Err(ClientError::DryRun {
+                method: request.method().clone(),
+                url: request.url().to_string(),
+                body: request
+                    .body()
+                    .and_then(|body| body.as_bytes())
+                    .map(|body| String::from_utf8_lossy(body).into_owned()),
+            });
+        }
+        let response = self.client.execute(request).await?;
+        if let Some(status) = RateLimitStatus::from_headers(response.headers()) {
+            *self.last_rate_limit_status.write().unwrap() = Some(status);
+        }
+        Ok(response)
+    }
+
+    /// Highest `context_slot` among the quotes received so far, `0` before the first one.
+    /// Shared by all clones of this client, see [`Self::with_context_slot_tracker`] to share it further.
+    pub fn latest_context_slot(&self) -> u64 {
+        self.latest_context_slot.load(Ordering::Relaxed)
+    }
+
+    /// Track the latest `context_slot` in `latest_context_slot`, e.g. to share it between clients
+    /// pointed at different endpoints
+    pub fn with_context_slot_tracker(mut self, latest_context_slot: Arc<AtomicU64>) -> Self {
+        self.latest_context_slot = latest_context_slot;
+        self
+    }
+
+    async fn deserialize_transformed<T: DeserializeOwned>(
+        &self,
+        response: Response,
+    ) -> Result<T, ClientError> {
+        let Some(response_transform) = &self.config.response_transform else {
+            return check_status_code_and_deserialize(response).await;
+        };
+        let value =
+            response_transform.transform(check_status_code_and_deserialize(response).await?);
+        serde_json::from_value(value.clone())
+            .map_err(|error| safe_u64::with_amount_path(error, &value).into())
+    }
+
+    /// Rate limit status from the most recent response carrying `X-RateLimit-*` headers,
+    /// shared by all clones of this client
+    pub fn last_rate_limit_status(&self) -> Option<RateLimitStatus> {
+        *self.last_rate_limit_status.read().unwrap()
+    }
+
+    /// Additional base paths serving the same API as `base_path`, for instance in other regions. Once
+    /// probed with [`Self::probe_endpoints`] or [`Self::spawn_endpoint_probe`], quotes and swaps go to
+    /// the fastest healthy endpoint, see [`Self::best_endpoint`]. Resets the ranking of previous probes.
+    pub fn with_endpoints(mut self, endpoints: Vec<String>) -> Self {
+        Arc::make_mut(&mut self.config).endpoints = endpoints;
+        self.endpoint_ranking = Arc::default();
+        self
+    }
+
+    /// The fastest endpoint healthy at the last probe, shared by all clones of this client, or
+    /// `base_path` before the first probe and when no endpoint is healthy
+    pub fn best_endpoint(&self) -> String {
+        self.endpoint_ranking
+            .read()
+            .unwrap()
+            .first()
+            .filter(|endpoint| endpoint.latency.is_some())
+            .map_or_else(
+                || self.base_path.clone(),
+                |endpoint| endpoint.base_path.clone(),
+            )
+    }
+
+    /// Endpoints ranked by the last probe, empty before the first probe
+    pub fn endpoint_ranking(&self) -> Vec<EndpointHealth> {
+        self.endpoint_ranking.read().unwrap().clone()
+    }
+
+    /// Time a `HEAD` request to `base_path` and each of [`Self::with_endpoints`] concurrently and rank
+    /// them by latency. Endpoints failing, answering with a server error or not within
+    /// [`ENDPOINT_PROBE_TIMEOUT`] are unhealthy. Nothing is sent in dry run mode, where the previous
+    /// ranking is returned.
+    pub async fn probe_endpoints(&self) -> Vec<EndpointHealth> {
+        if self.config.dry_run {
+            return self.endpoint_ranking();
+        }
+        let base_paths = std::iter::once(&self.base_path).chain(&self.config.endpoints);
+        let endpoints = join_all(base_paths.map(|base_path| async move {
+            let started_at = Instant::now();
+            let response = self
+                .client
+                .head(base_path)
+                .timeout(ENDPOINT_PROBE_TIMEOUT)
+                .send()
+                .await;
+            let healthy = response.is_ok_and(|response| !response.status().is_server_error());
+            EndpointHealth {
+                base_path: base_path.clone(),
+                latency: healthy.then(|| started_at.elapsed()),
+            }
+        }))
+        .await;
+        let ranking = endpoint::rank(endpoints);
+        *self.endpoint_ranking.write().unwrap() = ranking.clone();
+        ranking
+    }
+
+    /// Probe the endpoints every `interval` in a background task, starting immediately. The probe only
+    /// runs once spawned; abort the returned handle to stop it, which keeps the last ranking.
+    /// Must be called within a Tokio runtime.
+    pub fn spawn_endpoint_probe(&self, interval: Duration) -> tokio::task::JoinHandle<()> {
+        let client = self.clone();
+        tokio::spawn(async move {
+            loop {
+                client.probe_endpoints().await;
+                tokio::time::sleep(interval).await;
+            }
+        })
+    }
+
+    /// Open a connection to the API ahead of the first real request, saving the TCP and TLS handshakes
+    /// from its latency. Issues a `HEAD` request to the base path; any HTTP status counts as success,
+    /// only transport errors are returned. Nothing is sent in dry run mode, where this returns
+    /// `Ok(())`.
+    pub async fn warm_up(&self) -> Result<(), ClientError> {
+        if self.config.dry_run {
+            return Ok(());
+        }
+        self.send(self.client.head(&self.base_path)).await?;
+        Ok(())
+    }
+
     pub async fn quote(&self, quote_request: &QuoteRequest) -> Result<QuoteResponse, ClientError> {
-        let url = format!("{}/quote", self.base_path);
+        self.quote_with_headers(&self.best_endpoint(), quote_request)
+            .await
+            .map(|(quote_response, _)| quote_response)
+    }
+
+    async fn send_quote_request(
+        &self,
+        base_path: &str,
+        quote_request: &QuoteRequest,
+    ) -> Result<Response, ClientError> {
+        let url = join_url(base_path, "quote");
         let extra_args = quote_request.quote_args.clone();
-        let internal_quote_request = InternalQuoteRequest::from(quote_request.clone());
-        let response = self.client
-            .get(url)
-            .query(&internal_quote_request)
-            .query(&extra_args)
-            .send()
+        let mut internal_quote_request = InternalQuoteRequest::from(quote_request.clone());
+        if let Some(default_slippage_bps) = self.config.default_slippage_bps {
+            if internal_quote_request.slippage_bps == 0
+                && internal_quote_request.auto_slippage != Some(true)
+            {
+                internal_quote_request.slippage_bps = default_slippage_bps;
+            }
+        }
+        self.send(
+            self.query_in_param_case(self.client.get(url), &internal_quote_request)?
+                .query(&extra_args),
+        )
+        .await
+    }
+
+    async fn quote_with_headers(
+        &self,
+        base_path: &str,
+        quote_request: &QuoteRequest,
+    ) -> Result<(QuoteResponse, header::HeaderMap), ClientError> {
+        let response = self.send_quote_request(base_path, quote_request).await?;
+        let headers = response.headers().clone();
+        let quote_response: QuoteResponse = self.deserialize_transformed(response).await?;
+        self.latest_context_slot
+            .fetch_max(quote_response.context_slot, Ordering::Relaxed);
+        self.check_quote(&quote_response)?;
+        if let Some(max_auto_slippage_bps) = quote_request.max_auto_slippage_bps {
+            if quote_response.auto_slippage_clamped(max_auto_slippage_bps) {
+                log::warn!(
+                    "Auto slippage was clamped to max_auto_slippage_bps {max_auto_slippage_bps}"
+                );
+            }
+        }
+        if let Some(dexes) = &quote_request.dexes {
+            let allowed: Vec<&str> = dexes.split(',').map(str::trim).collect();
+            if !quote_response.uses_only_dexes(&allowed) {
+                log::warn!(
+                    "Route uses dexes outside of the requested {dexes}: {:?}",
+                    quote_response.route_labels()
+                );
+            }
+        }
+        Ok((quote_response, headers))
+    }
+
+    /// Like [`Self::quote`], but retries [transient](ClientError::is_transient) errors and those with
+    /// one of the [`DEFAULT_RETRYABLE_ERROR_CODES`], see [`Self::quote_within_retrying`]
+    pub async fn quote_within(
+        &self,
+        quote_request: &QuoteRequest,
+        total_budget: Duration,
+    ) -> Result<QuoteResponse, ClientError> {
+        self.quote_within_retrying(quote_request, total_budget, |error_code| {
+            DEFAULT_RETRYABLE_ERROR_CODES.contains(&error_code)
+        })
+        .await
+    }
+
+    /// Like [`Self::quote`], but retries [transient](ClientError::is_transient) errors, and errors
+    /// whose [`ApiErrorBody::error_code`] satisfies `retry_error_code`, with an exponential backoff
+    /// until `total_budget` has elapsed since the first attempt, then returns the last error.
+    /// The budget bounds when new attempts start, an attempt in flight is not cut short.
+    pub async fn quote_within_retrying(
+        &self,
+        quote_request: &QuoteRequest,
+        total_budget: Duration,
+        retry_error_code: impl Fn(&str) -> bool,
+    ) -> Result<QuoteResponse, ClientError> {
+        let is_retryable = |error: &ClientError| {
+            error.is_transient()
+                || error
+                    .api_error_body()
+                    .and_then(|body| body.error_code)
+                    .is_some_and(|error_code| retry_error_code(&error_code))
+        };
+        let started_at = Instant::now();
+        let mut retry_delay = QUOTE_WITHIN_INITIAL_RETRY_DELAY;
+        loop {
+            let error = match self.quote(quote_request).await {
+                Ok(quote_response) => return Ok(quote_response),
+                Err(error) if is_retryable(&error) => error,
+                Err(error) => return Err(error),
+            };
+            let remaining = total_budget.saturating_sub(started_at.elapsed());
+            if remaining < retry_delay {
+                return Err(error);
+            }
+            log::warn!("Quote failed, retrying in {retry_delay:?}: {error}");
+            tokio::time::sleep(retry_delay).await;
+            retry_delay *= 2;
+        }
+    }
+
+    /// Like [`Self::quote`], but fails with [`ClientError::UnknownFields`] when the response carries
+    /// fields this client does not model, to catch schema drift in CI rather than in production
+    pub async fn quote_strict(
+        &self,
+        quote_request: &QuoteRequest,
+    ) -> Result<QuoteResponse, ClientError> {
+        let response = self
+            .send_quote_request(&self.best_endpoint(), quote_request)
             .await?;
-        check_status_code_and_deserialize(response).await
+        let value = self.deserialize_transformed(response).await?;
+        let quote_response = QuoteResponse::from_json_strict(value)?;
+        self.latest_context_slot
+            .fetch_max(quote_response.context_slot, Ordering::Relaxed);
+        self.check_quote(&quote_response)?;
+        Ok(quote_response)
+    }
+
+    /// Quote `base` with only its slippage replaced, leaving `base` untouched
+    pub async fn quote_with_slippage(
+        &self,
+        base: &QuoteRequest,
+        slippage_bps: u16,
+    ) -> Result<QuoteResponse, ClientError> {
+        self.quote(&QuoteRequest {
+            slippage_bps,
+            ..base.clone()
+        })
+        .await
+    }
+
+    /// Quote the same request against two API base URLs concurrently, such as a self-hosted endpoint
+    /// and the public one, returning both quotes and how the second differs from the first
+    pub async fn compare_endpoints(
+        &self,
+        quote_request: &QuoteRequest,
+        base_path_a: &str,
+        base_path_b: &str,
+    ) -> Result<(QuoteResponse, QuoteResponse, QuoteDiff), ClientError> {
+        let ((quote_a, _), (quote_b, _)) = futures::try_join!(
+            self.quote_with_headers(base_path_a, quote_request),
+            self.quote_with_headers(base_path_b, quote_request)
+        )?;
+        let diff = quote_a.diff(&quote_b);
+        Ok((quote_a, quote_b, diff))
+    }
+
+    /// Reject quotes without any route, meaning both an empty `route_plan` and no output, and quotes
+    /// through a forbidden mint. A quote with an output but no route steps is passed through as is.
+    fn check_quote(&self, quote_response: &QuoteResponse) -> Result<(), ClientError> {
+        if quote_response.route_plan.is_empty() && quote_response.out_amount == 0 {
+            return Err(ClientError::NoRouteFound);
+        }
+        if let Some(mint) = self
+            .config
+            .forbidden_intermediate_mints
+            .iter()
+            .find(|mint| quote_response.routes_through_mint(mint))
+        {
+            return Err(ClientError::ForbiddenIntermediateMint(*mint));
+        }
+        let duplicate_amms = quote_response.route_plan.duplicate_amms();
+        if !duplicate_amms.is_empty() {
+            log::warn!("Route uses the same AMMs in several steps: {duplicate_amms:?}");
+        }
+        Ok(())
+    }
+
+    /// Quote `base` with its amount scaled by each multiplier, returning `(amount, price_impact_pct)` pairs
+    /// in the order of `multipliers`. Fails with [`ClientError::InvalidRequest`] before sending anything
+    /// when a multiplier is not a positive finite number or scales the amount beyond `u64`.
+    pub async fn probe_price_impact(
+        &self,
+        base: &QuoteRequest,
+        multipliers: &[f64],
+    ) -> Result<Vec<(u64, Decimal)>, ClientError> {
+        let amounts = multipliers
+            .iter()
+            .map(|multiplier| {
+                if !multiplier.is_finite() || *multiplier <= 0.0 {
+                    return Err(ClientError::InvalidRequest(format!(
+                        "price impact multiplier {multiplier} is not a positive finite number"
+                    )));
+                }
+                let amount = (base.amount as f64 * multiplier).round();
+                if amount >= u64::MAX as f64 {
+                    return Err(ClientError::InvalidRequest(format!(
+                        "price impact multiplier {multiplier} scales amount {} beyond u64",
+                        base.amount
+                    )));
+                }
+                Ok(amount as u64)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        try_join_all(amounts.into_iter().map(|amount| async move {
+            let quote_request = QuoteRequest {
+                amount,
+                ..base.clone()
+            };
+            let quote_response = self.quote(&quote_request).await?;
+            Ok((amount, quote_response.price_impact_pct))
+        }))
+        .await
+    }
+
+    /// Quote `base` without and with `minimize_slippage` concurrently, to measure what minimizing
+    /// slippage gains for this request, failing if either quote fails
+    pub async fn quote_compare_minimize(
+        &self,
+        base: &QuoteRequest,
+    ) -> Result<MinimizeSlippageComparison, ClientError> {
+        let quote_request = |minimize_slippage| QuoteRequest {
+            minimize_slippage: Some(minimize_slippage),
+            ..base.clone()
+        };
+        let plain_request = quote_request(false);
+        let minimized_request = quote_request(true);
+        let (plain, minimized) =
+            futures::try_join!(self.quote(&plain_request), self.quote(&minimized_request))?;
+        Ok(MinimizeSlippageComparison {
+            out_amount_delta: i128::from(minimized.out_amount) - i128::from(plain.out_amount),
+            plain,
+            minimized,
+        })
+    }
+
+    /// Quote `amount` as both ExactIn and ExactOut concurrently, failing if either quote fails
+    pub async fn quote_both_modes(
+        &self,
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        amount: u64,
+        slippage_bps: u16,
+    ) -> Result<BothModesQuote, ClientError> {
+        let quote_request = |swap_mode| QuoteRequest {
+            input_mint,
+            output_mint,
+            amount,
+            swap_mode: Some(swap_mode),
+            slippage_bps,
+            ..QuoteRequest::default()
+        };
+        let exact_in_request = quote_request(SwapMode::ExactIn);
+        let exact_out_request = quote_request(SwapMode::ExactOut);
+        let (exact_in, exact_out) = futures::try_join!(
+            self.quote(&exact_in_request),
+            self.quote(&exact_out_request)
+        )?;
+        Ok(BothModesQuote {
+            exact_in,
+            exact_out,
+        })
+    }
+
+    /// Quote like [`Self::quote`], recording when the response arrived and the server time of its
+    /// `Date` header, so the quote can be expired by age, see [`TimedQuote`]
+    pub async fn quote_timed(
+        &self,
+        quote_request: &QuoteRequest,
+    ) -> Result<TimedQuote, ClientError> {
+        let (response, headers) = self
+            .quote_with_headers(&self.best_endpoint(), quote_request)
+            .await?;
+        let server_time = headers
+            .get(header::DATE)
+            .and_then(|date| date.to_str().ok())
+            .and_then(|date| httpdate::parse_http_date(date).ok());
+        Ok(TimedQuote {
+            response,
+            fetched_at: std::time::Instant::now(),
+            server_time,
+        })
     }
 
     pub async fn swap(
@@ -92,26 +998,1718 @@ impl JupiterSwapApiClient {
         swap_request: &SwapRequest,
         extra_args: Option<HashMap<String, String>>,
     ) -> Result<SwapResponse, ClientError> {
-        let response = self.client
-            .post(format!("{}/swap", self.base_path))
-            .query(&extra_args)
-            .json(swap_request)
-            .send()
+        check_swap_request(swap_request, self.config.legacy_transaction_max_route_steps)?;
+        let request_builder = self
+            .client
+            .post(join_url(&self.best_endpoint(), "swap"))
+            .query(&extra_args);
+        let response = self
+            .send(self.json_in_param_case(request_builder, swap_request)?)
             .await?;
-        check_status_code_and_deserialize(response).await
+        let swap_response: SwapResponse = self.deserialize_transformed(response).await?;
+        self.check_simulation_error(&swap_response.simulation_error)?;
+        Ok(swap_response)
+    }
+
+    /// Quote `quote_request` and build the swap for `user_public_key` from it, failing with
+    /// [`ClientError::StaleQuote`] when the quote's `context_slot` is more than `max_slot_age` slots
+    /// behind `current_slot`, checked both before building and once the transaction is built.
+    /// `current_slot` is called for each check, e.g. reading a slot subscription kept by the caller.
+    pub async fn quote_and_swap(
+        &self,
+        quote_request: &QuoteRequest,
+        user_public_key: Pubkey,
+        config: TransactionConfig,
+        max_slot_age: u64,
+        current_slot: impl Fn() -> u64,
+    ) -> Result<(Arc<QuoteResponse>, SwapResponse), ClientError> {
+        let quote_response = Arc::new(self.quote(quote_request).await?);
+        let check_fresh = || {
+            let current_slot = current_slot();
+            if current_slot.saturating_sub(quote_response.context_slot) > max_slot_age {
+                return Err(ClientError::StaleQuote {
+                    context_slot: quote_response.context_slot,
+                    current_slot,
+                });
+            }
+            Ok(())
+        };
+        check_fresh()?;
+        let swap_request = SwapRequest {
+            user_public_key,
+            quote_response: quote_response.clone(),
+            config,
+        };
+        let swap_response = self.swap(&swap_request, None).await?;
+        check_fresh()?;
+        Ok((quote_response, swap_response))
+    }
+
+    /// Request the swap transaction, sign it with `signers` unless empty, and hand it to `send`, which
+    /// submits it through the caller's RPC client of choice, typically the nonblocking
+    /// `RpcClient::send_transaction`. With no signers the transaction is passed on as returned by the
+    /// API, for senders that sign it themselves.
+    pub async fn execute<F, E>(
+        &self,
+        swap_request: &SwapRequest,
+        signers: &[&dyn Signer],
+        send: impl FnOnce(VersionedTransaction) -> F,
+    ) -> Result<Signature, ClientError>
+    where
+        F: Future<Output = Result<Signature, E>>,
+        E: Into<Box<dyn std::error::Error + Send + Sync>>,
+    {
+        let mut transaction = self.swap(swap_request, None).await?.transaction()?;
+        if !signers.is_empty() {
+            transaction = VersionedTransaction::try_new(transaction.message, signers)?;
+        }
+        send(transaction)
+            .await
+            .map_err(|error| ClientError::SendFailed(error.into()))
     }
 
     pub async fn swap_instructions(
         &self,
         swap_request: &SwapRequest,
     ) -> Result<SwapInstructionsResponse, ClientError> {
-        let response = self.client
-            .post(format!("{}/swap-instructions", self.base_path))
-            .json(swap_request)
-            .send()
+        check_swap_request(swap_request, self.config.legacy_transaction_max_route_steps)?;
+        let request_builder = self
+            .client
+            .post(join_url(&self.best_endpoint(), "swap-instructions"));
+        let response = self
+            .send(self.json_in_param_case(request_builder, swap_request)?)
+            .await?;
+        let swap_instructions_response: SwapInstructionsResponse =
+            check_status_code_and_deserialize::<SwapInstructionsResponseInternal>(response)
+                .await?
+                .into();
+        self.check_simulation_error(&swap_instructions_response.simulation_error)?;
+        Ok(swap_instructions_response)
+    }
+
+    pub async fn token_info(&self, mint: &Pubkey) -> Result<TokenInfo, ClientError> {
+        let response = self
+            .send(self.client.get(join_url(
+                &self.token_api_base_path,
+                &format!("token/{mint}"),
+            )))
             .await?;
-        check_status_code_and_deserialize::<SwapInstructionsResponseInternal>(response)
+        check_status_code_and_deserialize(response).await
+    }
+
+    /// Decimals of `mint`, fetched from the token API once and cached afterwards
+    pub async fn token_decimals(&self, mint: &Pubkey) -> Result<u8, ClientError> {
+        if let Some(decimals) = self.cached_token_decimals(mint) {
+            return Ok(decimals);
+        }
+        let decimals = self.token_info(mint).await?.decimals;
+        self.token_decimals_cache
+            .write()
+            .unwrap()
+            .insert(*mint, decimals);
+        Ok(decimals)
+    }
+
+    pub fn cached_token_decimals(&self, mint: &Pubkey) -> Option<u8> {
+        self.token_decimals_cache.read().unwrap().get(mint).copied()
+    }
+
+    /// Decimals of every mint the quote touches, including intermediate mints, resolved concurrently
+    /// through [`Self::token_decimals`] so already cached mints are not fetched again. The v1 token API
+    /// at [`DEFAULT_TOKEN_API_BASE_PATH`] only looks up one mint per request and offers no batch endpoint,
+    /// so each distinct uncached mint costs one request.
+    pub async fn resolve_route_decimals(
+        &self,
+        quote_response: &QuoteResponse,
+    ) -> Result<HashMap<Pubkey, u8>, ClientError> {
+        let mints: HashSet<Pubkey> = [quote_response.input_mint, quote_response.output_mint]
+            .into_iter()
+            .chain(
+                quote_response
+                    .route_plan
+                    .iter()
+                    .flat_map(|step| [step.swap_info.input_mint, step.swap_info.output_mint]),
+            )
+            .collect();
+        try_join_all(mints.into_iter().map(|mint| async move {
+            Ok::<_, ClientError>((mint, self.token_decimals(&mint).await?))
+        }))
+        .await
+        .map(|decimals| decimals.into_iter().collect())
+    }
+
+    /// Quote `amount` of `input_mint` into each candidate output mint concurrently and return the
+    /// candidate with the highest output in UI terms, see [`Self::quote_candidates`]
+    pub async fn quote_best_output(
+        &self,
+        input_mint: Pubkey,
+        amount: u64,
+        candidates: &[Pubkey],
+        slippage_bps: u16,
+    ) -> Result<(Pubkey, QuoteResponse), ClientError> {
+        let mut candidate_quotes = self
+            .quote_candidates(input_mint, amount, candidates, slippage_bps)
+            .await?;
+        let best = candidate_quotes.candidates.swap_remove(0);
+        Ok((best.output_mint, best.quote_response))
+    }
+
+    /// Quote `amount` of `input_mint` into each candidate output mint concurrently, ranking the
+    /// candidates by output in UI terms, decimals being resolved via the token API, along with the
+    /// spread between the two best. Candidates that fail to quote or resolve are skipped.
+    pub async fn quote_candidates(
+        &self,
+        input_mint: Pubkey,
+        amount: u64,
+        candidates: &[Pubkey],
+        slippage_bps: u16,
+    ) -> Result<CandidateQuotes, ClientError> {
+        let results = join_all(candidates.iter().map(|output_mint| async move {
+            let quote_request = QuoteRequest {
+                input_mint,
+                output_mint: *output_mint,
+                amount,
+                slippage_bps,
+                ..QuoteRequest::default()
+            };
+            let (quote_response, decimals) =
+                futures::try_join!(self.quote(&quote_request), self.token_decimals(output_mint))?;
+            let ui_out_amount = base_to_ui_amount(quote_response.out_amount, decimals)
+                .ok_or(ClientError::UnsupportedDecimals(decimals))?;
+            Ok::<_, ClientError>(CandidateQuote {
+                output_mint: *output_mint,
+                ui_out_amount,
+                quote_response,
+            })
+        }))
+        .await;
+
+        let mut candidates: Vec<CandidateQuote> = results
+            .into_iter()
+            .filter_map(|result| {
+                result
+                    .inspect_err(|e| log::debug!("Skipping candidate output mint: {e}"))
+                    .ok()
+            })
+            .collect();
+        if candidates.is_empty() {
+            return Err(ClientError::NoCandidateQuote);
+        }
+        candidates.sort_by(|a, b| b.ui_out_amount.cmp(&a.ui_out_amount));
+        let spread_bps = candidates.get(1).map(|second| {
+            let best = candidates[0].ui_out_amount;
+            ((best - second.ui_out_amount) * Decimal::from(10_000))
+                .checked_div(best)
+                .and_then(|spread_bps| spread_bps.floor().to_u32())
+                .unwrap_or_default()
+        });
+        Ok(CandidateQuotes {
+            candidates,
+            spread_bps,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::atomic::{AtomicUsize, Ordering},
+        time::SystemTime,
+    };
+
+    use serde_json::json;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::*;
+
+    /// A request received by a mock server
+    pub(crate) struct MockRequest {
+        pub(crate) method: String,
+        /// Path and query
+        pub(crate) path: String,
+        pub(crate) headers: Vec<(String, String)>,
+        pub(crate) body: String,
+    }
+
+    impl MockRequest {
+        /// Value of the first header named `name`, compared case-insensitively
+        pub(crate) fn header(&self, name: &str) -> Option<&str> {
+            self.headers
+                .iter()
+                .find(|(header, _)| header.eq_ignore_ascii_case(name))
+                .map(|(_, value)| value.as_str())
+        }
+
+        /// Read one request off `stream`, its body included
+        async fn read(stream: &mut tokio::net::TcpStream) -> Self {
+            let mut buffer = Vec::new();
+            let mut chunk = vec![0; 16 * 1024];
+            let header_end = loop {
+                if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+                    break position + 4;
+                }
+                let read = stream.read(&mut chunk).await.unwrap();
+                if read == 0 {
+                    break buffer.len();
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+            };
+            let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+            let mut lines = head.lines();
+            let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+            let method = request_line.next().unwrap_or_default().to_string();
+            let path = request_line.next().unwrap_or_default().to_string();
+            let headers: Vec<(String, String)> = lines
+                .filter_map(|line| line.split_once(':'))
+                .map(|(name, value)| (name.trim().to_string(), value.trim().to_string()))
+                .collect();
+            let content_length = headers
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case("content-length"))
+                .and_then(|(_, value)| value.parse::<usize>().ok())
+                .unwrap_or_default();
+            while buffer.len() < header_end + content_length {
+                let read = stream.read(&mut chunk).await.unwrap();
+                if read == 0 {
+                    break;
+                }
+                buffer.extend_from_slice(&chunk[..read]);
+            }
+            let body_end = buffer.len().min(header_end + content_length);
+            Self {
+                method,
+                path,
+                headers,
+                body: String::from_utf8_lossy(&buffer[header_end..body_end]).into_owned(),
+            }
+        }
+    }
+
+    /// Serve HTTP requests on a local port, answering each with the status and JSON body
+    /// `respond` returns for the request path, query included. Returns the base URL.
+    pub(crate) async fn mock_server(
+        respond: impl Fn(&str) -> (u16, String) + Send + Sync + 'static,
+    ) -> String {
+        serve(Duration::ZERO, String::new(), move |request| {
+            respond(&request.path)
+        })
+        .await
+    }
+
+    /// Same as [`mock_server`], answering after inspecting the whole request, headers and body included
+    pub(crate) async fn mock_server_with_requests(
+        respond: impl Fn(&MockRequest) -> (u16, String) + Send + Sync + 'static,
+    ) -> String {
+        serve(Duration::ZERO, String::new(), respond).await
+    }
+
+    /// Same as [`mock_server`], answering every request after `delay`
+    pub(crate) async fn mock_server_with_delay(
+        delay: Duration,
+        respond: impl Fn(&str) -> (u16, String) + Send + Sync + 'static,
+    ) -> String {
+        serve(delay, String::new(), move |request| respond(&request.path)).await
+    }
+
+    /// Same as [`mock_server`], adding `headers`, each a `name: value` line, to every response.
+    /// A `content-type` among them replaces the default JSON one.
+    pub(crate) async fn mock_server_with_headers(
+        headers: &[&str],
+        respond: impl Fn(&str) -> (u16, String) + Send + Sync + 'static,
+    ) -> String {
+        let headers = headers
+            .iter()
+            .map(|header| format!("{header}\r\n"))
+            .collect();
+        serve(Duration::ZERO, headers, move |request| {
+            respond(&request.path)
+        })
+        .await
+    }
+
+    async fn serve(
+        delay: Duration,
+        headers: String,
+        respond: impl Fn(&MockRequest) -> (u16, String) + Send + Sync + 'static,
+    ) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let respond = Arc::new(respond);
+        let headers = if headers.to_ascii_lowercase().contains("content-type:") {
+            headers
+        } else {
+            format!("content-type: application/json\r\n{headers}")
+        };
+        let headers = Arc::new(headers);
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let respond = respond.clone();
+                let headers = headers.clone();
+                tokio::spawn(async move {
+                    let request = MockRequest::read(&mut stream).await;
+                    let (status, body) = respond(&request);
+                    tokio::time::sleep(delay).await;
+                    let response = format!(
+                        "HTTP/1.1 {status} Mock\r\ncontent-length: {}\r\n\
+                         connection: close\r\n{headers}\r\n{body}",
+                        body.len()
+                    );
+                    let _ = stream.write_all(response.as_bytes()).await;
+                });
+            }
+        });
+        format!("http://{address}")
+    }
+
+    pub(crate) fn quote_json() -> Value {
+        serde_json::from_str(include_str!("../tests/fixtures/quote_exact_in.json")).unwrap()
+    }
+
+    /// Candidate output mints, with their raw quoted output and decimals: `better` yields 2.0 UI
+    /// tokens, `worse` 1.5 despite a larger raw amount, `failing` cannot be quoted and
+    /// `unsupported` has more decimals than `Decimal` supports
+    struct Candidates {
+        better: Pubkey,
+        worse: Pubkey,
+        failing: Pubkey,
+        unsupported: Pubkey,
+    }
+
+    async fn candidates_client() -> (JupiterSwapApiClient, Candidates) {
+        let candidates = Candidates {
+            better: Pubkey::new_unique(),
+            worse: Pubkey::new_unique(),
+            failing: Pubkey::new_unique(),
+            unsupported: Pubkey::new_unique(),
+        };
+        let outputs = [
+            (candidates.better, 2_000_000u64, 6u8),
+            (candidates.worse, 1_500_000_000, 9),
+            (candidates.unsupported, 3_000_000, 30),
+        ];
+        let failing = candidates.failing;
+        let base_url = mock_server(move |path| {
+            if let Some(mint) = path.strip_prefix("/token/") {
+                return match outputs
+                    .iter()
+                    .find(|(output_mint, ..)| output_mint.to_string() == mint)
+                {
+                    Some((address, _, decimals)) => (
+                        200,
+                        json!({
+                            "address": address.to_string(),
+                            "name": "Token",
+                            "symbol": "TKN",
+                            "decimals": decimals,
+                        })
+                        .to_string(),
+                    ),
+                    None => (404, json!({ "error": "not found" }).to_string()),
+                };
+            }
+            if path.contains(&failing.to_string()) {
+                return (500, json!({ "error": "internal error" }).to_string());
+            }
+            let Some((output_mint, out_amount, _)) = outputs
+                .iter()
+                .find(|(output_mint, ..)| path.contains(&output_mint.to_string()))
+            else {
+                return (400, json!({ "error": "unknown mint" }).to_string());
+            };
+            let mut quote = quote_json();
+            quote["outputMint"] = json!(output_mint.to_string());
+            quote["outAmount"] = json!(out_amount.to_string());
+            (200, quote.to_string())
+        })
+        .await;
+        let mut client = JupiterSwapApiClient::new(base_url.clone(), String::new()).unwrap();
+        client.token_api_base_path = base_url;
+        (client, candidates)
+    }
+
+    /// Client of a mock quoting `out_amount` without any route step
+    async fn routeless_quote_client(out_amount: u64) -> JupiterSwapApiClient {
+        let base_url = mock_server(move |_| {
+            let mut quote = quote_json();
+            quote["routePlan"] = json!([]);
+            quote["outAmount"] = json!(out_amount.to_string());
+            (200, quote.to_string())
+        })
+        .await;
+        JupiterSwapApiClient::new(base_url, String::new()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn quote_without_route_or_output_is_no_route_found() {
+        let client = routeless_quote_client(0).await;
+        let result = client.quote(&QuoteRequest::default()).await;
+        assert!(
+            matches!(result, Err(ClientError::NoRouteFound)),
+            "{result:?}"
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_with_output_but_empty_route_plan_is_accepted() {
+        let client = routeless_quote_client(145_307_225).await;
+        let quote_response = client.quote(&QuoteRequest::default()).await.unwrap();
+        assert!(quote_response.route_plan.is_empty());
+        assert_eq!(quote_response.out_amount, 145_307_225);
+    }
+
+    #[tokio::test]
+    async fn probe_price_impact_quotes_each_scaled_amount() {
+        let requested_amounts = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let base_url = mock_server({
+            let requested_amounts = requested_amounts.clone();
+            move |path| {
+                let amount = path
+                    .split(['?', '&'])
+                    .find_map(|param| param.strip_prefix("amount="))
+                    .unwrap()
+                    .to_string();
+                requested_amounts.lock().unwrap().push(amount);
+                (200, quote_json().to_string())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let base = QuoteRequest {
+            amount: 1_000_000,
+            ..QuoteRequest::default()
+        };
+
+        let probes = client
+            .probe_price_impact(&base, &[0.5, 1.0, 2.5, 10.0])
+            .await
+            .unwrap();
+
+        let amounts: Vec<u64> = probes.iter().map(|(amount, _)| *amount).collect();
+        assert_eq!(amounts, [500_000, 1_000_000, 2_500_000, 10_000_000]);
+        let mut requested_amounts = requested_amounts.lock().unwrap().clone();
+        requested_amounts.sort_by_key(|amount| amount.parse::<u64>().unwrap());
+        assert_eq!(
+            requested_amounts,
+            ["500000", "1000000", "2500000", "10000000"]
+        );
+    }
+
+    #[tokio::test]
+    async fn probe_price_impact_rejects_invalid_multipliers() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let base_url = mock_server({
+            let requests = requests.clone();
+            move |_| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                (200, quote_json().to_string())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let base = QuoteRequest {
+            amount: 1_000_000,
+            ..QuoteRequest::default()
+        };
+
+        for multiplier in [0.0, -1.0, f64::NAN, f64::INFINITY, f64::MAX] {
+            let result = client.probe_price_impact(&base, &[1.0, multiplier]).await;
+            assert!(
+                matches!(result, Err(ClientError::InvalidRequest(_))),
+                "{multiplier}: {result:?}"
+            );
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn amount_overflow_error_names_the_field_path() {
+        let base_url = mock_server(|_| {
+            let mut quote = quote_json();
+            quote["routePlan"][0]["swapInfo"]["outAmount"] = json!("18446744073709551616");
+            (200, quote.to_string())
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let error = client.quote(&QuoteRequest::default()).await.unwrap_err();
+        assert!(matches!(error, ClientError::JsonDeserializationError(_)));
+        assert!(
+            error.to_string().starts_with(
+                "Failed to deserialize response: routePlan[0].swapInfo.outAmount: \
+                 amount 18446744073709551616 exceeds u64 range"
+            ),
+            "{error}"
+        );
+    }
+
+    #[tokio::test]
+    async fn resolve_route_decimals_fetches_each_route_mint_once() {
+        let mut quote = quote_json();
+        let (usdc, bonk) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut second_hop = quote["routePlan"][0].clone();
+        quote["routePlan"][0]["swapInfo"]["outputMint"] = json!(usdc.to_string());
+        second_hop["swapInfo"]["inputMint"] = json!(usdc.to_string());
+        second_hop["swapInfo"]["outputMint"] = json!(bonk.to_string());
+        quote["routePlan"] = json!([quote["routePlan"][0], second_hop]);
+        quote["outputMint"] = json!(bonk.to_string());
+        let quote_response: QuoteResponse = serde_json::from_value(quote).unwrap();
+        let sol = quote_response.input_mint;
+        let expected = HashMap::from([(sol, 9), (usdc, 6), (bonk, 5)]);
+
+        let requested_mints = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let base_url = mock_server({
+            let requested_mints = requested_mints.clone();
+            let expected = expected.clone();
+            move |path| {
+                let mint: Pubkey = path.strip_prefix("/token/").unwrap().parse().unwrap();
+                requested_mints.lock().unwrap().push(mint);
+                (
+                    200,
+                    json!({
+                        "address": mint.to_string(),
+                        "name": "Token",
+                        "symbol": "TKN",
+                        "decimals": expected[&mint],
+                    })
+                    .to_string(),
+                )
+            }
+        })
+        .await;
+        let mut client = JupiterSwapApiClient::new(base_url.clone(), String::new()).unwrap();
+        client.token_api_base_path = base_url;
+
+        let decimals = client
+            .resolve_route_decimals(&quote_response)
+            .await
+            .unwrap();
+        assert_eq!(decimals, expected);
+        let cached = client
+            .resolve_route_decimals(&quote_response)
+            .await
+            .unwrap();
+        assert_eq!(cached, expected);
+        let mut requested_mints = requested_mints.lock().unwrap().clone();
+        requested_mints.sort();
+        let mut route_mints: Vec<Pubkey> = expected.keys().copied().collect();
+        route_mints.sort();
+        assert_eq!(requested_mints, route_mints);
+    }
+
+    #[tokio::test]
+    async fn clones_share_the_token_decimals_cache() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let mint = Pubkey::new_unique();
+        let base_url = mock_server({
+            let requests = requests.clone();
+            move |_| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                let token = json!({
+                    "address": mint.to_string(),
+                    "name": "Token",
+                    "symbol": "TKN",
+                    "decimals": 6,
+                });
+                (200, token.to_string())
+            }
+        })
+        .await;
+        let mut client = JupiterSwapApiClient::new(base_url.clone(), String::new()).unwrap();
+        client.token_api_base_path = base_url;
+        let clone = client.clone();
+
+        assert_eq!(client.token_decimals(&mint).await.unwrap(), 6);
+        assert_eq!(clone.cached_token_decimals(&mint), Some(6));
+        assert_eq!(clone.token_decimals(&mint).await.unwrap(), 6);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn quote_best_output_picks_the_largest_ui_output() {
+        let (client, candidates) = candidates_client().await;
+        let input_mint = Pubkey::new_unique();
+        let (output_mint, quote_response) = client
+            .quote_best_output(
+                input_mint,
+                1_000_000_000,
+                &[
+                    candidates.worse,
+                    candidates.failing,
+                    candidates.unsupported,
+                    candidates.better,
+                ],
+                50,
+            )
+            .await
+            .unwrap();
+        assert_eq!(output_mint, candidates.better);
+        assert_eq!(quote_response.out_amount, 2_000_000);
+    }
+
+    #[tokio::test]
+    async fn quote_best_output_fails_without_any_candidate() {
+        let (client, candidates) = candidates_client().await;
+        let result = client
+            .quote_best_output(
+                Pubkey::new_unique(),
+                1_000_000_000,
+                &[candidates.failing, candidates.unsupported],
+                50,
+            )
+            .await;
+        assert!(matches!(result, Err(ClientError::NoCandidateQuote)));
+    }
+
+    #[tokio::test]
+    async fn quote_candidates_reports_the_spread_of_the_two_best() {
+        let (client, candidates) = candidates_client().await;
+        let candidate_quotes = client
+            .quote_candidates(
+                Pubkey::new_unique(),
+                1_000_000_000,
+                &[candidates.worse, candidates.better, candidates.failing],
+                50,
+            )
+            .await
+            .unwrap();
+        let output_mints: Vec<Pubkey> = candidate_quotes
+            .candidates
+            .iter()
+            .map(|candidate| candidate.output_mint)
+            .collect();
+        assert_eq!(output_mints, [candidates.better, candidates.worse]);
+        assert_eq!(candidate_quotes.best().ui_out_amount, Decimal::from(2));
+        assert_eq!(candidate_quotes.spread_bps, Some(2_500));
+    }
+
+    /// Mock endpoint answering quotes with `out_amount`
+    async fn quote_endpoint(delay: Duration, out_amount: u64) -> String {
+        mock_server_with_delay(delay, move |_| {
+            let mut quote = quote_json();
+            quote["outAmount"] = json!(out_amount.to_string());
+            (200, quote.to_string())
+        })
+        .await
+    }
+
+    #[tokio::test]
+    async fn quotes_go_to_the_fastest_endpoint() {
+        let slow = quote_endpoint(Duration::from_millis(300), 1).await;
+        let fast = quote_endpoint(Duration::ZERO, 2).await;
+        let client = JupiterSwapApiClient::new(slow.clone(), String::new())
+            .unwrap()
+            .with_endpoints(vec![fast.clone()]);
+        let quote_request = QuoteRequest::default();
+
+        assert_eq!(client.best_endpoint(), slow);
+        assert_eq!(client.quote(&quote_request).await.unwrap().out_amount, 1);
+
+        let ranking = client.probe_endpoints().await;
+        let ranked: Vec<&str> = ranking
+            .iter()
+            .map(|endpoint| endpoint.base_path.as_str())
+            .collect();
+        assert_eq!(ranked, [fast.as_str(), slow.as_str()]);
+        assert!(ranking[0].latency < ranking[1].latency);
+        assert_eq!(client.best_endpoint(), fast);
+        assert_eq!(client.quote(&quote_request).await.unwrap().out_amount, 2);
+    }
+
+    #[tokio::test]
+    async fn unhealthy_endpoints_are_ranked_last() {
+        let failing =
+            mock_server(|_| (500, json!({ "error": "internal error" }).to_string())).await;
+        let healthy = quote_endpoint(Duration::from_millis(50), 1).await;
+        let client = JupiterSwapApiClient::new(failing.clone(), String::new())
+            .unwrap()
+            .with_endpoints(vec![healthy.clone()]);
+        let ranking = client.probe_endpoints().await;
+        assert_eq!(ranking[0].base_path, healthy);
+        assert!(ranking[0].latency.is_some());
+        assert_eq!(
+            ranking[1],
+            EndpointHealth {
+                base_path: failing,
+                latency: None
+            }
+        );
+        assert_eq!(client.best_endpoint(), healthy);
+    }
+
+    #[tokio::test]
+    async fn background_probe_ranks_endpoints_until_aborted() {
+        let slow = quote_endpoint(Duration::from_millis(200), 1).await;
+        let fast = quote_endpoint(Duration::ZERO, 2).await;
+        let client = JupiterSwapApiClient::new(slow, String::new())
+            .unwrap()
+            .with_endpoints(vec![fast.clone()]);
+        let probe = client.spawn_endpoint_probe(Duration::from_secs(60));
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while client.endpoint_ranking().is_empty() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .unwrap();
+        probe.abort();
+        assert_eq!(client.best_endpoint(), fast);
+    }
+
+    #[tokio::test]
+    async fn timed_quote_ages_in_server_time() {
+        let server_time = SystemTime::now() - Duration::from_secs(30);
+        let date = format!("date: {}", httpdate::fmt_http_date(server_time));
+        let base_url =
+            mock_server_with_headers(&[&date], |_| (200, quote_json().to_string())).await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let timed_quote = client.quote_timed(&QuoteRequest::default()).await.unwrap();
+        let sent_at = timed_quote.server_time.unwrap();
+        assert_eq!(
+            httpdate::fmt_http_date(sent_at),
+            httpdate::fmt_http_date(server_time)
+        );
+        assert!(timed_quote.age() >= Duration::from_secs(29));
+        assert!(timed_quote.is_expired(Duration::from_secs(10)));
+    }
+
+    #[tokio::test]
+    async fn timed_quote_ages_in_local_time_without_date_header() {
+        let base_url = mock_server(|_| (200, quote_json().to_string())).await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let timed_quote = client.quote_timed(&QuoteRequest::default()).await.unwrap();
+        assert_eq!(timed_quote.server_time, None);
+        assert!(timed_quote.age() < Duration::from_secs(10));
+        assert!(!timed_quote.is_expired(Duration::from_secs(10)));
+    }
+
+    async fn counting_client() -> (Arc<AtomicUsize>, JupiterSwapApiClient) {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let base_url = mock_server({
+            let requests = requests.clone();
+            move |_| {
+                requests.fetch_add(1, Ordering::SeqCst);
+                (200, String::new())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        (requests, client)
+    }
+
+    #[tokio::test]
+    async fn warm_up_reaches_the_api() {
+        let (requests, client) = counting_client().await;
+        client.warm_up().await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn warm_up_sends_nothing_in_dry_run() {
+        let (requests, client) = counting_client().await;
+        client.with_dry_run(true).warm_up().await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn quote_in_dry_run_returns_the_request_unsent() {
+        let (requests, client) = counting_client().await;
+        let quote_request = QuoteRequest {
+            input_mint: Pubkey::new_unique(),
+            output_mint: Pubkey::new_unique(),
+            amount: 1_000_000,
+            slippage_bps: 50,
+            ..QuoteRequest::default()
+        };
+        match client.with_dry_run(true).quote(&quote_request).await {
+            Err(ClientError::DryRun { method, url, body }) => {
+                assert_eq!(method, Method::GET);
+                assert!(url.contains("/quote?"), "{url}");
+                assert!(
+                    url.contains(&format!("inputMint={}", quote_request.input_mint)),
+                    "{url}"
+                );
+                assert!(url.contains("amount=1000000"), "{url}");
+                assert!(url.contains("slippageBps=50"), "{url}");
+                assert_eq!(body, None);
+            }
+            other => panic!("expected a dry run, got {other:?}"),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[tokio::test]
+    async fn swap_in_dry_run_returns_the_request_unsent() {
+        let (requests, client) = counting_client().await;
+        let swap_request = legacy_swap_request(1);
+        match client.with_dry_run(true).swap(&swap_request, None).await {
+            Err(ClientError::DryRun { method, url, body }) => {
+                assert_eq!(method, Method::POST);
+                assert!(url.ends_with("/swap"), "{url}");
+                let body: Value = serde_json::from_str(&body.unwrap()).unwrap();
+                assert_eq!(body, serde_json::to_value(&swap_request).unwrap());
+            }
+            other => panic!("expected a dry run, got {other:?}"),
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 0);
+    }
+
+    #[test]
+    fn snake_case_renames_only_top_level_fields() {
+        let mut quote = quote_json();
+        quote["routePlan"][0]["swapInfo"]["ammSpecificField"] = json!("kept");
+        let swap_request = SwapRequest {
+            quote_response: Arc::new(serde_json::from_value(quote).unwrap()),
+            config: TransactionConfig {
+                wrap_and_unwrap_sol: false,
+                as_legacy_transaction: true,
+                ..TransactionConfig::default()
+            },
+            ..legacy_swap_request(1)
+        };
+
+        let body = rename_keys_to_snake_case(serde_json::to_value(&swap_request).unwrap());
+
+        assert!(body.get("user_public_key").is_some());
+        assert_eq!(body["wrap_and_unwrap_sol"], json!(false));
+        assert_eq!(body["as_legacy_transaction"], json!(true));
+        let quote_response = &body["quote_response"];
+        assert_eq!(
+            *quote_response,
+            serde_json::to_value(swap_request.quote()).unwrap()
+        );
+        assert_eq!(quote_response["inAmount"], json!("1000000000"));
+        assert_eq!(
+            quote_response["routePlan"][0]["swapInfo"]["ammSpecificField"],
+            json!("kept")
+        );
+    }
+
+    /// Client of a mock answering swaps with an unsigned transfer transaction paid by `payer`
+    /// A swap response carrying an unsigned legacy transfer paid by `payer`
+    fn unsigned_swap_response(payer: Pubkey) -> String {
+        use base64::Engine;
+        use solana_sdk::{hash::Hash, message::Message, system_instruction};
+
+        let message = Message::new_with_blockhash(
+            &[system_instruction::transfer(
+                &payer,
+                &Pubkey::new_unique(),
+                1,
+            )],
+            Some(&payer),
+            &Hash::new_unique(),
+        );
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: solana_sdk::message::VersionedMessage::Legacy(message),
+        };
+        let swap_transaction = base64::engine::general_purpose::STANDARD
+            .encode(bincode::serialize(&transaction).unwrap());
+        json!({
+            "swapTransaction": swap_transaction,
+            "lastValidBlockHeight": 1,
+            "prioritizationFeeLamports": 0,
+        })
+        .to_string()
+    }
+
+    async fn unsigned_swap_client(payer: Pubkey) -> JupiterSwapApiClient {
+        let swap_response = unsigned_swap_response(payer);
+        let base_url = mock_server(move |_| (200, swap_response.clone())).await;
+        JupiterSwapApiClient::new(base_url, String::new()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn execute_hands_the_signed_transaction_to_the_sender() {
+        use solana_sdk::signature::Keypair;
+
+        let payer = Keypair::new();
+        let client = unsigned_swap_client(payer.pubkey()).await;
+        let sent = Arc::new(std::sync::Mutex::new(None));
+
+        let signature = client
+            .execute(&legacy_swap_request(1), &[&payer], |transaction| {
+                let sent = sent.clone();
+                async move {
+                    tokio::task::yield_now().await;
+                    let signature = transaction.signatures[0];
+                    *sent.lock().unwrap() = Some(transaction);
+                    Ok::<_, std::io::Error>(signature)
+                }
+            })
             .await
-            .map(Into::into)
+            .unwrap();
+
+        let sent = sent.lock().unwrap().take().unwrap();
+        assert_eq!(sent.signatures, [signature]);
+        assert_ne!(signature, Signature::default());
+        assert!(sent.verify_with_results().into_iter().all(|valid| valid));
+    }
+
+    #[tokio::test]
+    async fn execute_reports_sender_failures() {
+        let client = unsigned_swap_client(Pubkey::new_unique()).await;
+        let result = client
+            .execute(&legacy_swap_request(1), &[], |transaction| async move {
+                assert_eq!(transaction.signatures, [Signature::default()]);
+                Err::<Signature, _>("node is behind")
+            })
+            .await;
+        let Err(ClientError::SendFailed(error)) = result else {
+            panic!("expected a send failure, got {result:?}");
+        };
+        assert_eq!(error.to_string(), "node is behind");
+    }
+
+    fn legacy_swap_request(route_steps: usize) -> SwapRequest {
+        let mut quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        quote_response.route_plan = vec![quote_response.route_plan[0].clone(); route_steps];
+        SwapRequest {
+            user_public_key: Pubkey::new_unique(),
+            quote_response: Arc::new(quote_response),
+            config: TransactionConfig {
+                as_legacy_transaction: true,
+                ..TransactionConfig::default()
+            },
+        }
+    }
+
+    #[test]
+    fn legacy_route_step_limit_is_off_by_default() {
+        assert!(check_swap_request(&legacy_swap_request(5), None).is_ok());
+    }
+
+    #[test]
+    fn legacy_route_step_limit_rejects_longer_routes() {
+        assert!(check_swap_request(&legacy_swap_request(2), Some(2)).is_ok());
+        assert!(matches!(
+            check_swap_request(&legacy_swap_request(3), Some(2)),
+            Err(ClientError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn join_url_inserts_exactly_one_slash() {
+        for base_url in ["https://api.jup.ag/swap/v1", "https://api.jup.ag/swap/v1/"] {
+            for path in ["quote", "/quote"] {
+                assert_eq!(join_url(base_url, path), "https://api.jup.ag/swap/v1/quote");
+            }
+        }
+        assert_eq!(
+            join_url("https://example.com/jupiter/v6//", "//swap-instructions"),
+            "https://example.com/jupiter/v6/swap-instructions"
+        );
+        assert_eq!(
+            join_url("http://127.0.0.1:8080", "swap"),
+            "http://127.0.0.1:8080/swap"
+        );
+    }
+
+    fn versioned_base_path(base_path: &str, version: ApiVersion) -> String {
+        JupiterSwapApiClient::new(base_path.to_string(), String::new())
+            .unwrap()
+            .with_api_version(version)
+            .base_path
+    }
+
+    #[test]
+    fn with_api_version_appends_the_version_path() {
+        assert_eq!(
+            versioned_base_path("https://api.jup.ag", ApiVersion::SwapV1),
+            "https://api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            versioned_base_path("https://quote-api.jup.ag/", ApiVersion::V6),
+            "https://quote-api.jup.ag/v6"
+        );
+    }
+
+    #[test]
+    fn with_api_version_replaces_the_version_path() {
+        assert_eq!(
+            versioned_base_path("https://api.jup.ag/v6/", ApiVersion::SwapV1),
+            "https://api.jup.ag/swap/v1"
+        );
+        assert_eq!(
+            versioned_base_path("https://example.com/jupiter/swap/v1", ApiVersion::V6),
+            "https://example.com/jupiter/v6"
+        );
+        assert_eq!(
+            versioned_base_path("https://example.com/jupiter/v6", ApiVersion::V6),
+            "https://example.com/jupiter/v6"
+        );
+        assert_eq!(
+            join_url(
+                &versioned_base_path("https://example.com/jupiter", ApiVersion::SwapV1),
+                "quote"
+            ),
+            "https://example.com/jupiter/swap/v1/quote"
+        );
+    }
+
+    fn same_origin(original: &str, target: &str) -> bool {
+        is_same_origin(&original.parse().unwrap(), &target.parse().unwrap())
+    }
+
+    #[test]
+    fn same_host_redirect_follows_same_origin() {
+        assert!(same_origin(
+            "https://api.jup.ag/swap/v1/quote",
+            "https://api.jup.ag/swap/v2/quote"
+        ));
+        assert!(same_origin(
+            "https://api.jup.ag/quote",
+            "https://api.jup.ag:443/quote"
+        ));
+    }
+
+    #[test]
+    fn same_host_redirect_stops_on_other_scheme() {
+        assert!(!same_origin(
+            "https://api.jup.ag/quote",
+            "http://api.jup.ag/quote"
+        ));
+    }
+
+    #[test]
+    fn same_host_redirect_stops_on_other_host() {
+        assert!(!same_origin(
+            "https://api.jup.ag/quote",
+            "https://evil.example/quote"
+        ));
+    }
+
+    #[test]
+    fn same_host_redirect_stops_on_other_port() {
+        assert!(!same_origin(
+            "https://api.jup.ag/quote",
+            "https://api.jup.ag:8443/quote"
+        ));
+    }
+
+    /// Quote through a server redirecting to another host, `localhost` instead of `127.0.0.1`,
+    /// returning the result and the number of requests the other host received
+    async fn quote_through_cross_host_redirect(
+        redirect_policy: RedirectPolicy,
+    ) -> (Result<QuoteResponse, ClientError>, usize) {
+        let redirected = Arc::new(AtomicUsize::new(0));
+        let other_host = mock_server({
+            let redirected = redirected.clone();
+            move |_| {
+                redirected.fetch_add(1, Ordering::SeqCst);
+                (200, quote_json().to_string())
+            }
+        })
+        .await
+        .replace("127.0.0.1", "localhost");
+        let location = format!("location: {other_host}/quote");
+        let base_url = mock_server_with_headers(&[&location], |_| (302, String::new())).await;
+        let client = JupiterSwapApiClient::new(base_url, String::new())
+            .unwrap()
+            .with_redirect_policy(redirect_policy)
+            .unwrap();
+        let result = client.quote(&QuoteRequest::default()).await;
+        (result, redirected.load(Ordering::SeqCst))
+    }
+
+    #[tokio::test]
+    async fn same_host_policy_does_not_follow_cross_host_redirects() {
+        for redirect_policy in [RedirectPolicy::SameHost, RedirectPolicy::None] {
+            let (result, redirected) = quote_through_cross_host_redirect(redirect_policy).await;
+            let Err(ClientError::RequestFailed { status, .. }) = result else {
+                panic!("{redirect_policy:?}: expected the redirect response, got {result:?}");
+            };
+            assert_eq!(status, reqwest::StatusCode::FOUND, "{redirect_policy:?}");
+            assert_eq!(redirected, 0, "{redirect_policy:?}");
+        }
+    }
+
+    #[tokio::test]
+    async fn limited_policy_follows_cross_host_redirects() {
+        let (result, redirected) =
+            quote_through_cross_host_redirect(RedirectPolicy::Limited(1)).await;
+        assert_eq!(result.unwrap().out_amount, 145_307_225);
+        assert_eq!(redirected, 1);
+    }
+
+    #[tokio::test]
+    async fn limited_policy_fails_beyond_its_limit() {
+        let (result, redirected) =
+            quote_through_cross_host_redirect(RedirectPolicy::Limited(0)).await;
+        assert!(
+            matches!(&result, Err(ClientError::DeserializationError(error)) if error.is_redirect()),
+            "{result:?}"
+        );
+        assert_eq!(redirected, 0);
+    }
+
+    /// Signs each request with a header carrying the length of its body
+    struct BodyLengthSigner;
+
+    impl RequestInterceptor for BodyLengthSigner {
+        fn on_request(&self, request: &mut reqwest::Request) {
+            let length = request
+                .body()
+                .and_then(|body| body.as_bytes())
+                .map_or(0, <[u8]>::len);
+            request
+                .headers_mut()
+                .insert("x-body-length", header::HeaderValue::from(length));
+        }
+    }
+
+    #[tokio::test]
+    async fn interceptor_headers_reach_the_server() {
+        let swap_response = unsigned_swap_response(Pubkey::new_unique());
+        let received = Arc::new(std::sync::Mutex::new(None));
+        let base_url = mock_server_with_requests({
+            let received = received.clone();
+            move |request| {
+                *received.lock().unwrap() = Some((
+                    request.method.clone(),
+                    request.header("x-body-length").map(str::to_string),
+                    request.body.len(),
+                ));
+                (200, swap_response.clone())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new())
+            .unwrap()
+            .with_interceptor(BodyLengthSigner);
+
+        client.swap(&legacy_swap_request(1), None).await.unwrap();
+        let (method, body_length, received_body_length) = received.lock().unwrap().take().unwrap();
+        assert_eq!(method, "POST");
+        assert!(received_body_length > 0);
+        assert_eq!(body_length, Some(received_body_length.to_string()));
+    }
+
+    #[tokio::test]
+    async fn quote_through_a_forbidden_mint_is_rejected() {
+        let intermediate_mint = Pubkey::new_unique();
+        let base_url = mock_server(move |_| {
+            let mut quote = quote_json();
+            let mut second_step = quote["routePlan"][0].clone();
+            quote["routePlan"][0]["swapInfo"]["outputMint"] = json!(intermediate_mint.to_string());
+            second_step["swapInfo"]["inputMint"] = json!(intermediate_mint.to_string());
+            second_step["swapInfo"]["ammKey"] = json!(Pubkey::new_unique().to_string());
+            quote["routePlan"].as_array_mut().unwrap().push(second_step);
+            (200, quote.to_string())
+        })
+        .await;
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+
+        let allowed = client
+            .clone()
+            .with_forbidden_intermediate_mints([quote_response.output_mint]);
+        assert_eq!(
+            allowed
+                .quote(&QuoteRequest::default())
+                .await
+                .unwrap()
+                .route_plan
+                .len(),
+            2
+        );
+
+        let forbidding = client.with_forbidden_intermediate_mints([intermediate_mint]);
+        let result = forbidding.quote(&QuoteRequest::default()).await;
+        let Err(ClientError::ForbiddenIntermediateMint(mint)) = result else {
+            panic!("expected a forbidden mint error, got {result:?}");
+        };
+        assert_eq!(mint, intermediate_mint);
+    }
+
+    #[tokio::test]
+    async fn default_slippage_applies_only_when_unset() {
+        let paths = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let base_url = mock_server({
+            let paths = paths.clone();
+            move |path| {
+                paths.lock().unwrap().push(path.to_string());
+                (200, quote_json().to_string())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new())
+            .unwrap()
+            .with_default_slippage_bps(75);
+
+        let requests = [
+            QuoteRequest::default(),
+            QuoteRequest {
+                slippage_bps: 30,
+                ..QuoteRequest::default()
+            },
+            QuoteRequest {
+                auto_slippage: Some(true),
+                ..QuoteRequest::default()
+            },
+        ];
+        for quote_request in &requests {
+            client.quote(quote_request).await.unwrap();
+        }
+        let paths = paths.lock().unwrap();
+        assert!(paths[0].contains("slippageBps=75"), "{}", paths[0]);
+        assert!(paths[1].contains("slippageBps=30"), "{}", paths[1]);
+        assert!(paths[2].contains("slippageBps=0"), "{}", paths[2]);
+    }
+
+    #[tokio::test]
+    async fn rate_limit_headers_are_surfaced_after_a_request() {
+        let base_url = mock_server_with_headers(
+            &[
+                "x-ratelimit-limit: 600",
+                "x-ratelimit-remaining: 599",
+                "x-ratelimit-reset: 1700000060",
+            ],
+            |_| (200, quote_json().to_string()),
+        )
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        assert_eq!(client.last_rate_limit_status(), None);
+
+        client.quote(&QuoteRequest::default()).await.unwrap();
+        assert_eq!(
+            client.last_rate_limit_status(),
+            Some(RateLimitStatus {
+                limit: Some(600),
+                remaining: Some(599),
+                reset: Some(1_700_000_060),
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_both_modes_keeps_each_mode_apart() {
+        let base_url = mock_server(|path| {
+            let mut quote = quote_json();
+            if path.contains("swapMode=ExactOut") {
+                quote["swapMode"] = json!("ExactOut");
+                quote["inAmount"] = json!("6882000000");
+                quote["outAmount"] = json!("1000000000");
+            } else {
+                assert!(path.contains("swapMode=ExactIn"), "{path}");
+            }
+            (200, quote.to_string())
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+
+        let both = client
+            .quote_both_modes(
+                quote_response.input_mint,
+                quote_response.output_mint,
+                1_000_000_000,
+                50,
+            )
+            .await
+            .unwrap();
+        assert_eq!(both.exact_in.swap_mode, SwapMode::ExactIn);
+        assert_eq!(both.exact_in.out_amount, 145_307_225);
+        assert_eq!(both.exact_out.swap_mode, SwapMode::ExactOut);
+        assert_eq!(both.exact_out.in_amount, 6_882_000_000);
+    }
+
+    #[test]
+    fn referral_fee_account_uses_the_configured_program() {
+        let (referral_account, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let client = JupiterSwapApiClient::new(String::new(), String::new()).unwrap();
+        assert_eq!(
+            client.referral_fee_account(referral_account, mint),
+            referral::derive_referral_fee_account(referral_account, mint)
+        );
+
+        let referral_program_id = Pubkey::new_unique();
+        let client = client.with_referral_program_id(referral_program_id);
+        let fee_account = client.referral_fee_account(referral_account, mint);
+        assert_eq!(
+            fee_account,
+            referral::derive_referral_fee_account_with_program_id(
+                referral_account,
+                mint,
+                &referral_program_id
+            )
+        );
+        assert_ne!(
+            fee_account,
+            referral::derive_referral_fee_account(referral_account, mint)
+        );
+    }
+
+    #[tokio::test]
+    async fn html_gateway_errors_are_reported_with_a_body_snippet() {
+        let page = format!(
+            "<html><head><title>502 Bad Gateway</title></head><body>{}</body></html>",
+            "upstream unavailable ".repeat(50)
+        );
+        let base_url = mock_server_with_headers(&["content-type: text/html"], move |path| {
+            let status = if path.starts_with("/quote") { 502 } else { 200 };
+            (status, page.clone())
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+
+        let result = client.quote(&QuoteRequest::default()).await;
+        let Err(error @ ClientError::RequestFailed { status, body }) = &result else {
+            panic!("expected a failed request, got {result:?}");
+        };
+        assert_eq!(*status, reqwest::StatusCode::BAD_GATEWAY);
+        assert!(body.starts_with("<html><head><title>502 Bad Gateway"));
+        assert!(body.ends_with("..."));
+        assert_eq!(body.chars().count(), NON_JSON_BODY_SNIPPET_CHARS + 3);
+        assert!(error.api_error_body().is_none());
+        assert!(error.is_transient());
+
+        // An HTML page served with a success status is not deserialized either
+        let result = client.swap(&legacy_swap_request(1), None).await;
+        let Err(ClientError::RequestFailed { status, .. }) = &result else {
+            panic!("expected a failed request, got {result:?}");
+        };
+        assert_eq!(*status, reqwest::StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn compare_endpoints_diffs_the_second_quote_against_the_first() {
+        let base_path_a = mock_server(|_| (200, quote_json().to_string())).await;
+        let base_path_b = mock_server(|_| {
+            let mut quote = quote_json();
+            quote["outAmount"] = json!("145300000");
+            quote["priceImpactPct"] = json!("0.0003");
+            quote["contextSlot"] = json!(299283765);
+            quote["routePlan"][0]["swapInfo"]["label"] = json!("Raydium");
+            (200, quote.to_string())
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(String::new(), String::new()).unwrap();
+
+        let (quote_a, quote_b, diff) = client
+            .compare_endpoints(&QuoteRequest::default(), &base_path_a, &base_path_b)
+            .await
+            .unwrap();
+        assert_eq!(quote_a.out_amount, 145_307_225);
+        assert_eq!(quote_b.out_amount, 145_300_000);
+        assert_eq!(
+            diff,
+            QuoteDiff {
+                in_amount: 0,
+                out_amount: -7_225,
+                price_impact_pct: Decimal::new(2, 4),
+                context_slot: 2,
+                route_changed: true,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_with_slippage_overrides_only_the_slippage() {
+        let paths = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let base_url = mock_server({
+            let paths = paths.clone();
+            move |path| {
+                paths.lock().unwrap().push(path.to_string());
+                (200, quote_json().to_string())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let base = QuoteRequest {
+            amount: 1_000_000_000,
+            slippage_bps: 50,
+            only_direct_routes: Some(true),
+            ..QuoteRequest::default()
+        };
+        let base_before = serde_json::to_value(&base).unwrap();
+
+        client.quote_with_slippage(&base, 300).await.unwrap();
+        assert_eq!(serde_json::to_value(&base).unwrap(), base_before);
+        let path = &paths.lock().unwrap()[0];
+        assert!(path.contains("slippageBps=300"), "{path}");
+        assert!(path.contains("amount=1000000000"), "{path}");
+        assert!(path.contains("onlyDirectRoutes=true"), "{path}");
+    }
+
+    #[tokio::test]
+    async fn api_version_header_reaches_the_server() {
+        let versions = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let base_url = mock_server_with_requests({
+            let versions = versions.clone();
+            move |request| {
+                versions
+                    .lock()
+                    .unwrap()
+                    .push(request.header(API_VERSION_HEADER).map(str::to_string));
+                (200, quote_json().to_string())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        client.quote(&QuoteRequest::default()).await.unwrap();
+
+        let client = client.with_api_version_header("2".to_string()).unwrap();
+        client.quote(&QuoteRequest::default()).await.unwrap();
+        assert_eq!(*versions.lock().unwrap(), [None, Some("2".to_string())]);
+    }
+
+    #[tokio::test]
+    async fn simulation_errors_fail_the_swap_when_opted_in() {
+        let mut swap_response: Value =
+            serde_json::from_str(&unsigned_swap_response(Pubkey::new_unique())).unwrap();
+        swap_response["simulationError"] = json!({
+            "errorCode": "INSUFFICIENT_FUNDS",
+            "error": "Attempt to debit an account but found no record of a prior credit.",
+        });
+        let swap_response = swap_response.to_string();
+        let base_url = mock_server(move |_| (200, swap_response.clone())).await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+
+        let swap_response = client.swap(&legacy_swap_request(1), None).await.unwrap();
+        let simulation_error = swap_response.simulation_error.unwrap();
+        assert_eq!(simulation_error.error_code, "INSUFFICIENT_FUNDS");
+        assert!(simulation_error.error.starts_with("Attempt to debit"));
+
+        let client = client.with_fail_on_simulation_error(true);
+        let result = client.swap(&legacy_swap_request(1), None).await;
+        let Err(ClientError::SimulationFailed(simulation_error)) = result else {
+            panic!("expected a simulation failure, got {result:?}");
+        };
+        assert_eq!(simulation_error.error_code, "INSUFFICIENT_FUNDS");
+    }
+
+    /// A client whose quotes always fail with `status` and `body`, counting the attempts
+    async fn failing_quote_client(
+        status: u16,
+        body: Value,
+    ) -> (Arc<AtomicUsize>, JupiterSwapApiClient) {
+        let attempts = Arc::new(AtomicUsize::new(0));
+        let base_url = mock_server({
+            let attempts = attempts.clone();
+            move |_| {
+                attempts.fetch_add(1, Ordering::SeqCst);
+                (status, body.to_string())
+            }
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        (attempts, client)
+    }
+
+    #[tokio::test]
+    async fn quote_within_stops_retrying_once_the_budget_is_spent() {
+        let (attempts, client) = failing_quote_client(503, json!({"error": "unavailable"})).await;
+
+        // Attempts start at 0, 100 and 300 ms, the next one would be 700 ms in
+        let started_at = Instant::now();
+        let result = client
+            .quote_within(&QuoteRequest::default(), Duration::from_millis(350))
+            .await;
+        let Err(ClientError::RequestFailed { status, .. }) = result else {
+            panic!("expected the last failure, got {result:?}");
+        };
+        assert_eq!(status, reqwest::StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert!(started_at.elapsed() < Duration::from_millis(700));
+
+        attempts.store(0, Ordering::SeqCst);
+        let result = client
+            .quote_within(&QuoteRequest::default(), Duration::ZERO)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn latest_context_slot_only_moves_forward() {
+        let slots = [100_u64, 90, 120];
+        let polls = AtomicUsize::new(0);
+        let base_url = mock_server(move |_| {
+            let mut quote = quote_json();
+            quote["contextSlot"] = json!(slots[polls.fetch_add(1, Ordering::SeqCst)]);
+            (200, quote.to_string())
+        })
+        .await;
+        let tracker = Arc::new(AtomicU64::new(0));
+        let client = JupiterSwapApiClient::new(base_url, String::new())
+            .unwrap()
+            .with_context_slot_tracker(tracker.clone());
+        assert_eq!(client.latest_context_slot(), 0);
+
+        let mut observed = Vec::new();
+        for _ in slots {
+            client.quote(&QuoteRequest::default()).await.unwrap();
+            observed.push(client.latest_context_slot());
+        }
+        assert_eq!(observed, [100, 100, 120]);
+        assert_eq!(tracker.load(Ordering::SeqCst), 120);
+    }
+
+    /// Renames the `outAmountRaw` field of a quirky endpoint back to `outAmount`
+    struct RenameOutAmount;
+
+    impl ResponseTransform for RenameOutAmount {
+        fn transform(&self, mut value: Value) -> Value {
+            if let Some(object) = value.as_object_mut() {
+                if let Some(out_amount) = object.remove("outAmountRaw") {
+                    object.insert("outAmount".to_string(), out_amount);
+                }
+            }
+            value
+        }
+    }
+
+    #[tokio::test]
+    async fn response_transform_renames_a_field_before_deserialization() {
+        let base_url = mock_server(|_| {
+            let mut quote = quote_json();
+            let out_amount = quote.as_object_mut().unwrap().remove("outAmount").unwrap();
+            quote["outAmountRaw"] = out_amount;
+            (200, quote.to_string())
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+        let result = client.quote(&QuoteRequest::default()).await;
+        assert!(
+            matches!(result, Err(ClientError::JsonDeserializationError(_))),
+            "{result:?}"
+        );
+
+        let client = client.with_response_transform(RenameOutAmount);
+        let quote_response = client.quote(&QuoteRequest::default()).await.unwrap();
+        assert_eq!(quote_response.out_amount, 145_307_225);
+    }
+
+    #[tokio::test]
+    async fn quote_compare_minimize_reports_what_minimizing_gains() {
+        let base_url = mock_server(|path| {
+            let mut quote = quote_json();
+            if path.contains("minimizeSlippage=true") {
+                quote["outAmount"] = json!("145400000");
+                quote["usesQuoteMinimizingSlippage"] = json!(true);
+            } else {
+                assert!(path.contains("minimizeSlippage=false"), "{path}");
+            }
+            (200, quote.to_string())
+        })
+        .await;
+        let client = JupiterSwapApiClient::new(base_url, String::new()).unwrap();
+
+        let comparison = client
+            .quote_compare_minimize(&QuoteRequest::default())
+            .await
+            .unwrap();
+        assert_eq!(comparison.plain.out_amount, 145_307_225);
+        assert!(!comparison.plain.minimized_slippage());
+        assert_eq!(comparison.minimized.out_amount, 145_400_000);
+        assert!(comparison.minimized.minimized_slippage());
+        assert_eq!(comparison.out_amount_delta, 92_775);
+    }
+
+    /// A client quoting [`quote_json`] and building [`unsigned_swap_response`], counting swap requests
+    async fn quote_and_swap_client() -> (Arc<AtomicUsize>, JupiterSwapApiClient) {
+        let swaps = Arc::new(AtomicUsize::new(0));
+        let counter = swaps.clone();
+        let quote_response = quote_json().to_string();
+        let swap_response = unsigned_swap_response(Pubkey::new_unique());
+        let base_url = mock_server(move |path| {
+            if path.starts_with("/quote") {
+                (200, quote_response.clone())
+            } else {
+                counter.fetch_add(1, Ordering::SeqCst);
+                (200, swap_response.clone())
+            }
+        })
+        .await;
+        (
+            swaps,
+            JupiterSwapApiClient::new(base_url, String::new()).unwrap(),
+        )
+    }
+
+    #[tokio::test]
+    async fn quote_and_swap_rejects_a_stale_quote() {
+        let context_slot = quote_json()["contextSlot"].as_u64().unwrap();
+        let (swaps, client) = quote_and_swap_client().await;
+        let quote_and_swap = |current_slot: Arc<dyn Fn() -> u64>| {
+            let client = &client;
+            async move {
+                client
+                    .quote_and_swap(
+                        &QuoteRequest::default(),
+                        Pubkey::new_unique(),
+                        TransactionConfig::default(),
+                        10,
+                        move || current_slot(),
+                    )
+                    .await
+            }
+        };
+
+        let (quote_response, _) = quote_and_swap(Arc::new(move || context_slot + 10))
+            .await
+            .unwrap();
+        assert_eq!(quote_response.context_slot, context_slot);
+        assert_eq!(swaps.load(Ordering::SeqCst), 1);
+
+        // Already stale once quoted: no transaction is built
+        let result = quote_and_swap(Arc::new(move || context_slot + 11)).await;
+        assert!(
+            matches!(result, Err(ClientError::StaleQuote { context_slot: slot, current_slot })
+                if slot == context_slot && current_slot == context_slot + 11),
+            "{result:?}"
+        );
+        assert_eq!(swaps.load(Ordering::SeqCst), 1);
+
+        // Fresh when quoted, stale once the transaction is built
+        let checks = Arc::new(AtomicUsize::new(0));
+        let result = quote_and_swap(Arc::new(move || {
+            context_slot + 100 * checks.fetch_add(1, Ordering::SeqCst) as u64
+        }))
+        .await;
+        assert!(
+            matches!(result, Err(ClientError::StaleQuote { current_slot, .. })
+                if current_slot == context_slot + 100),
+            "{result:?}"
+        );
+        assert_eq!(swaps.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn quote_within_retries_only_retryable_error_codes() {
+        let budget = Duration::from_millis(150);
+
+        // Attempts at 0 and 100 ms, the next one would be 300 ms in
+        let (attempts, client) =
+            failing_quote_client(400, json!({"errorCode": "COULD_NOT_FIND_ANY_ROUTE"})).await;
+        let result = client.quote_within(&QuoteRequest::default(), budget).await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        let (attempts, client) =
+            failing_quote_client(400, json!({"errorCode": "TOKEN_NOT_TRADABLE"})).await;
+        let result = client.quote_within(&QuoteRequest::default(), budget).await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
+
+        // A custom predicate replaces the default codes
+        let (attempts, client) =
+            failing_quote_client(400, json!({"errorCode": "TOKEN_NOT_TRADABLE"})).await;
+        let result = client
+            .quote_within_retrying(&QuoteRequest::default(), budget, |error_code| {
+                error_code == "TOKEN_NOT_TRADABLE"
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 2);
+
+        let (attempts, client) =
+            failing_quote_client(400, json!({"errorCode": "COULD_NOT_FIND_ANY_ROUTE"})).await;
+        let result = client
+            .quote_within_retrying(&QuoteRequest::default(), budget, |_| false)
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts.load(Ordering::SeqCst), 1);
     }
 }
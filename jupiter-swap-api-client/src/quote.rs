@@ -1,14 +1,23 @@
 //! Quote data structure for quoting and quote response
 //!
 
-use std::{collections::HashMap, str::FromStr};
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    str::FromStr,
+    time::{Duration, Instant, SystemTime},
+};
 
+use crate::amounts::{apply_slippage, base_to_ui_amount, RoundingMode, SlippageDirection};
 use crate::route_plan_with_metadata::RoutePlanWithMetadata;
 use crate::serde_helpers::field_as_string;
 use crate::serde_helpers::option_field_as_string;
+use crate::serde_helpers::safe_u64;
+use crate::{curl_command, join_url, ClientError};
 use anyhow::{anyhow, Error};
-use rust_decimal::Decimal;
+use rust_decimal::{prelude::ToPrimitive, Decimal, RoundingStrategy};
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use solana_sdk::pubkey::Pubkey;
 
 #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
@@ -23,10 +32,10 @@ pub struct SwapInfo {
     #[serde(with = "field_as_string")]
     pub output_mint: Pubkey,
     /// An estimation of the input amount into the AMM
-    #[serde(with = "field_as_string")]
+    #[serde(with = "safe_u64")]
     pub in_amount: u64,
     /// An estimation of the output amount into the AMM
-    #[serde(with = "field_as_string")]
+    #[serde(with = "safe_u64")]
     pub out_amount: u64,
     #[serde(
         default,
@@ -40,6 +49,9 @@ pub struct SwapInfo {
         with = "option_field_as_string"
     )]
     pub fee_mint: Option<Pubkey>,
+    /// AMM specific fields not modeled above, kept as is so they survive re-serialization
+    #[serde(flatten)]
+    pub extra: HashMap<String, Value>,
 }
 
 #[derive(Serialize, Deserialize, Default, PartialEq, Clone, Debug)]
@@ -61,12 +73,20 @@ impl FromStr for SwapMode {
     }
 }
 
-#[derive(Serialize, Debug, Clone, Default)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct ComputeUnitScore {
     pub max_penalty_bps: Option<f64>,
 }
 
-#[derive(Serialize, Debug, Default, Clone)]
+/// Compute unit score echoed back in quote responses, whose fields are camelCase unlike the
+/// `compute_unit_score` request parameter
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AppliedComputeUnitScore {
+    pub max_penalty_bps: Option<f64>,
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct QuoteRequest {
     #[serde(with = "field_as_string")]
@@ -122,6 +142,296 @@ pub struct QuoteRequest {
     pub token_category_based_intermediate_tokens: Option<bool>,
 }
 
+/// 常用路由偏好的命名预设，通过[`QuoteRequestBuilder::routing_preset`]作为`routing_constraints`参数发送。
+/// 序列化和[`fmt::Display`]均得到该参数的字符串值。
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Copy, Clone)]
+#[serde(rename_all = "camelCase")]
+pub enum RoutingConstraintPreset {
+    /// 不限制路由，以获得最优价格，即`bestPrice`
+    BestPrice,
+    /// 优先缩短路由搜索时间，即`lowLatency`
+    LowLatency,
+    /// 优先选择涉及账户较少的路由，便于与其他指令组合进同一交易，即`minimalAccounts`
+    MinimalAccounts,
+}
+
+impl fmt::Display for RoutingConstraintPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            RoutingConstraintPreset::BestPrice => "bestPrice",
+            RoutingConstraintPreset::LowLatency => "lowLatency",
+            RoutingConstraintPreset::MinimalAccounts => "minimalAccounts",
+        })
+    }
+}
+
+/// 按类型划分的dex，通过[`QuoteRequestBuilder::exclude_category`]展开为对应的dex标签
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum DexCategory {
+    /// 集中流动性做市
+    Clmm,
+    /// 恒定乘积做市
+    ConstantProduct,
+    /// 稳定币曲线做市
+    Stable,
+    /// 链上订单簿
+    OrderBook,
+    /// 报价由做市商自行维护的私有做市
+    Proprietary,
+}
+
+impl DexCategory {
+    /// 该类型包含的dex标签，与`/program-id-to-label`返回的标签一致。
+    /// 新上线的dex不会自动归类，需要时请配合[`QuoteRequestBuilder::excluded_dexes`]补充。
+    pub fn labels(&self) -> &'static [&'static str] {
+        match self {
+            DexCategory::Clmm => &[
+                "Whirlpool",
+                "Raydium CLMM",
+                "Meteora DLMM",
+                "Invariant",
+                "Crema",
+            ],
+            DexCategory::ConstantProduct => &["Raydium", "Raydium CP", "Meteora", "Orca V2"],
+            DexCategory::Stable => &["Saber", "Mercurial", "Stabble Stable Swap"],
+            DexCategory::OrderBook => &["Phoenix", "OpenBook V2"],
+            DexCategory::Proprietary => &["SolFi", "Obric V2", "ZeroFi"],
+        }
+    }
+}
+
+/// `safe_defaults`预设使用的保守滑点，以基点为单位
+pub const SAFE_DEFAULT_SLIPPAGE_BPS: u16 = 50;
+
+impl QuoteRequest {
+    /// 生成可直接运行的curl命令，便于在程序外复现报价请求，API密钥以占位符代替。
+    /// 参数无法编码为查询字符串时返回[`ClientError::InvalidRequest`]。
+    pub fn to_curl(&self, base_url: &str) -> Result<String, ClientError> {
+        let invalid_query = |e: serde_qs::Error| {
+            ClientError::InvalidRequest(format!("cannot encode quote query: {e}"))
+        };
+        let mut query = serde_qs::to_string(&InternalQuoteRequest::from(self.clone()))
+            .map_err(invalid_query)?;
+        if let Some(quote_args) = self.quote_args.as_ref().filter(|args| !args.is_empty()) {
+            query.push('&');
+            query.push_str(&serde_qs::to_string(quote_args).map_err(invalid_query)?);
+        }
+        Ok(curl_command(
+            "GET",
+            &format!("{}?{query}", join_url(base_url, "quote")),
+            None,
+        ))
+    }
+
+    pub fn builder(input_mint: Pubkey, output_mint: Pubkey, amount: u64) -> QuoteRequestBuilder {
+        QuoteRequestBuilder::new(input_mint, output_mint, amount)
+    }
+
+    /// 向`quote_args`中插入一个参数，保留其他已有参数，同名参数的旧值会被替换；`quote_args`为`None`时会先创建。
+    /// 这些参数以原名追加到报价请求的查询字符串中。
+    pub fn set_quote_arg(&mut self, key: impl Into<String>, value: impl Into<String>) {
+        self.quote_args
+            .get_or_insert_with(HashMap::new)
+            .insert(key.into(), value.into());
+    }
+
+    /// [`Self::set_quote_arg`]的链式版本，便于连续插入多个参数
+    pub fn with_quote_arg(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.set_quote_arg(key, value);
+        self
+    }
+
+    /// 列出已设置的已弃用参数及推荐的替代方式，每个参数一条说明。自动滑点系列参数已被构建交换时的动态滑点
+    /// [`TransactionConfig::dynamic_slippage`](crate::transaction_config::TransactionConfig::dynamic_slippage)取代。
+    pub fn deprecation_warnings(&self) -> Vec<String> {
+        let dynamic_slippage = "set dynamic_slippage in the TransactionConfig of the swap instead";
+        let mut warnings = Vec::new();
+        if self.auto_slippage.is_some() {
+            warnings.push(format!("auto_slippage is deprecated, {dynamic_slippage}"));
+        }
+        if self.max_auto_slippage_bps.is_some() {
+            warnings.push(format!(
+                "max_auto_slippage_bps is deprecated, {dynamic_slippage} with its max_bps"
+            ));
+        }
+        if self.compute_auto_slippage {
+            warnings.push(format!(
+                "compute_auto_slippage is deprecated, {dynamic_slippage}"
+            ));
+        }
+        if self.auto_slippage_collision_usd_value.is_some() {
+            warnings.push(format!(
+                "auto_slippage_collision_usd_value is deprecated, {dynamic_slippage}"
+            ));
+        }
+        warnings
+    }
+
+    /// 支付场景的预设，与[`TransactionConfig::payment_preset`](crate::transaction_config::TransactionConfig::payment_preset)配合使用：
+    /// - `swap_mode`设为ExactOut，`amount`即收款方应收到的精确数量；
+    /// - 滑点设为[`SAFE_DEFAULT_SLIPPAGE_BPS`]，ExactOut下滑点作用于输入代币，即最多多付的数量；
+    /// - 不启用`auto_slippage`，使付款方可预知的最大支出保持固定。
+    pub fn payment_preset(input_mint: Pubkey, output_mint: Pubkey, exact_out_amount: u64) -> Self {
+        Self {
+            input_mint,
+            output_mint,
+            amount: exact_out_amount,
+            swap_mode: Some(SwapMode::ExactOut),
+            slippage_bps: SAFE_DEFAULT_SLIPPAGE_BPS,
+            auto_slippage: Some(false),
+            ..Self::default()
+        }
+    }
+}
+
+/// [`QuoteRequest`]的构建器，未设置的字段保持默认值
+#[derive(Debug, Default, Clone)]
+pub struct QuoteRequestBuilder {
+    request: QuoteRequest,
+}
+
+impl QuoteRequestBuilder {
+    pub fn new(input_mint: Pubkey, output_mint: Pubkey, amount: u64) -> Self {
+        Self {
+            request: QuoteRequest {
+                input_mint,
+                output_mint,
+                amount,
+                ..QuoteRequest::default()
+            },
+        }
+    }
+
+    /// 适合新手的起始配置：
+    /// - 启用`restrict_intermediate_tokens`，降低高滑点错误率，代价是可能错过少数更优但流动性较差的路由；
+    /// - 滑点设为[`SAFE_DEFAULT_SLIPPAGE_BPS`]，波动较大的代币可能因此更容易超出滑点而失败。
+    pub fn safe_defaults(mut self) -> Self {
+        self.request.restrict_intermediate_tokens = Some(true);
+        self.request.slippage_bps = SAFE_DEFAULT_SLIPPAGE_BPS;
+        self
+    }
+
+    /// 将`routing_constraints`设为预设的字符串值，不改动其他参数
+    pub fn routing_preset(mut self, preset: RoutingConstraintPreset) -> Self {
+        self.request.routing_constraints = Some(preset.to_string());
+        self
+    }
+
+    pub fn swap_mode(mut self, swap_mode: SwapMode) -> Self {
+        self.request.swap_mode = Some(swap_mode);
+        self
+    }
+
+    pub fn slippage_bps(mut self, slippage_bps: u16) -> Self {
+        self.request.slippage_bps = slippage_bps;
+        self
+    }
+
+    pub fn platform_fee_bps(mut self, platform_fee_bps: u8) -> Self {
+        self.request.platform_fee_bps = Some(platform_fee_bps);
+        self
+    }
+
+    pub fn dexes(mut self, dexes: impl Into<Dexes>) -> Self {
+        self.request.dexes = Some(dexes.into());
+        self
+    }
+
+    pub fn excluded_dexes(mut self, excluded_dexes: impl Into<Dexes>) -> Self {
+        self.request.excluded_dexes = Some(excluded_dexes.into());
+        self
+    }
+
+    /// 将该类型的dex标签追加到`excluded_dexes`，已排除的标签不会重复出现
+    pub fn exclude_category(mut self, category: DexCategory) -> Self {
+        let mut excluded_dexes: Vec<String> = self
+            .request
+            .excluded_dexes
+            .as_deref()
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|label| !label.is_empty())
+            .map(String::from)
+            .collect();
+        for label in category.labels() {
+            if !excluded_dexes.iter().any(|excluded| excluded == label) {
+                excluded_dexes.push(label.to_string());
+            }
+        }
+        self.request.excluded_dexes = Some(excluded_dexes.join(","));
+        self
+    }
+
+    pub fn only_direct_routes(mut self, only_direct_routes: bool) -> Self {
+        self.request.only_direct_routes = Some(only_direct_routes);
+        self
+    }
+
+    pub fn as_legacy_transaction(mut self, as_legacy_transaction: bool) -> Self {
+        self.request.as_legacy_transaction = Some(as_legacy_transaction);
+        self
+    }
+
+    pub fn restrict_intermediate_tokens(mut self, restrict_intermediate_tokens: bool) -> Self {
+        self.request.restrict_intermediate_tokens = Some(restrict_intermediate_tokens);
+        self
+    }
+
+    pub fn max_accounts(mut self, max_accounts: usize) -> Self {
+        self.request.max_accounts = Some(max_accounts);
+        self
+    }
+
+    pub fn quote_args(mut self, quote_args: impl Into<HashMap<String, String>>) -> Self {
+        self.request.quote_args = Some(quote_args.into());
+        self
+    }
+
+    pub fn build(self) -> QuoteRequest {
+        self.request
+    }
+
+    /// 与[`Self::build`]相同，但会将[`QuoteRequest::deprecation_warnings`]逐条输出为警告日志
+    pub fn build_with_deprecation_warnings(self) -> QuoteRequest {
+        for warning in self.request.deprecation_warnings() {
+            log::warn!("{warning}");
+        }
+        self.request
+    }
+}
+
+/// `quote_args`的构建器，生成与手动构造相同的映射。
+///
+/// API没有公开各`quote_type`可用的参数键，因此只提供[`Self::raw`]，
+/// 不提供类型化的setter和`QuoteType`枚举，以免猜测出API不认可的键。
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct QuoteArgs {
+    args: HashMap<String, String>,
+}
+
+impl QuoteArgs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 设置任意参数，同名参数会被覆盖
+    pub fn raw(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.args.insert(key.into(), value.into());
+        self
+    }
+
+    pub fn build(self) -> HashMap<String, String> {
+        self.args
+    }
+}
+
+impl From<QuoteArgs> for HashMap<String, String> {
+    fn from(quote_args: QuoteArgs) -> Self {
+        quote_args.build()
+    }
+}
+
 // Essentially the same as QuoteRequest, but without the extra args
 // as we pass the extra args separately
 #[derive(Serialize, Debug, Default, Clone)]
@@ -170,6 +480,8 @@ pub struct InternalQuoteRequest {
     pub quote_type: Option<String>,
     // enable only full liquid markets as intermediate tokens
     pub prefer_liquid_dexes: Option<bool>,
+    /// Routing constraints
+    pub routing_constraints: Option<String>,
 }
 
 impl From<QuoteRequest> for InternalQuoteRequest {
@@ -194,6 +506,7 @@ impl From<QuoteRequest> for InternalQuoteRequest {
             max_accounts: request.max_accounts,
             quote_type: request.quote_type,
             prefer_liquid_dexes: request.prefer_liquid_dexes,
+            routing_constraints: request.routing_constraints,
         }
     }
 }
@@ -204,7 +517,7 @@ type Dexes = String;
 #[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct PlatformFee {
-    #[serde(with = "field_as_string")]
+    #[serde(with = "safe_u64")]
     pub amount: u64,
     pub fee_bps: u8,
 }
@@ -214,27 +527,1128 @@ pub struct PlatformFee {
 pub struct QuoteResponse {
     #[serde(with = "field_as_string")]
     pub input_mint: Pubkey,
-    #[serde(with = "field_as_string")]
+    #[serde(with = "safe_u64")]
     pub in_amount: u64,
     #[serde(with = "field_as_string")]
     pub output_mint: Pubkey,
-    #[serde(with = "field_as_string")]
+    #[serde(with = "safe_u64")]
     pub out_amount: u64,
     /// Not used by build transaction
-    #[serde(with = "field_as_string")]
+    #[serde(with = "safe_u64")]
     pub other_amount_threshold: u64,
     pub swap_mode: SwapMode,
     pub slippage_bps: u16,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub computed_auto_slippage: Option<u16>,
+    /// Set when the request enabled `minimize_slippage` and the route was searched with a larger
+    /// probe amount
     #[serde(skip_serializing_if = "Option::is_none")]
     pub uses_quote_minimizing_slippage: Option<bool>,
+    /// Amount the route was searched with when minimizing slippage, see [`Self::used_probe_amount`]
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        with = "option_field_as_string"
+    )]
+    pub probe_amount: Option<u64>,
+    /// Compute unit score echoed back when the quote was requested with `compute_unit_score`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub applied_compute_unit_score: Option<AppliedComputeUnitScore>,
     pub platform_fee: Option<PlatformFee>,
     pub price_impact_pct: Decimal,
     pub route_plan: RoutePlanWithMetadata,
     #[serde(default)]
     pub context_slot: u64,
+    /// Server side quote computation time in seconds, see [`QuoteResponse::server_duration`]
     #[serde(default)]
     pub time_taken: f64,
 }
 
+/// Non-null fields of `original` absent from `reserialized` were dropped on deserialization.
+/// Absent null fields are modeled options skipped when serializing.
+fn collect_unknown_fields(
+    original: &Value,
+    reserialized: &Value,
+    path: &str,
+    unknown_fields: &mut Vec<String>,
+) {
+    match (original, reserialized) {
+        (Value::Object(original), Value::Object(reserialized)) => {
+            for (key, original_value) in original {
+                let field_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{path}.{key}")
+                };
+                match reserialized.get(key) {
+                    Some(reserialized_value) => collect_unknown_fields(
+                        original_value,
+                        reserialized_value,
+                        &field_path,
+                        unknown_fields,
+                    ),
+                    None if !original_value.is_null() => unknown_fields.push(field_path),
+                    None => {}
+                }
+            }
+        }
+        (Value::Array(original), Value::Array(reserialized)) => {
+            for (index, (original_value, reserialized_value)) in
+                original.iter().zip(reserialized).enumerate()
+            {
+                collect_unknown_fields(
+                    original_value,
+                    reserialized_value,
+                    &format!("{path}[{index}]"),
+                    unknown_fields,
+                );
+            }
+        }
+        _ => {}
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum OrderSide {
+    Buy,
+    Sell,
+}
+
+/// See [`QuoteResponse::order_summary`]
+#[derive(Debug, PartialEq, Clone)]
+pub struct OrderSummary {
+    pub side: OrderSide,
+    /// Quote tokens per base token
+    pub price: Decimal,
+    pub base_size: Decimal,
+    pub quote_size: Decimal,
+    /// Platform fee, in quote tokens
+    pub fee: Decimal,
+}
+
+/// Lightweight view of a route plan step, retaining only what most callers need
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct CompactHop {
+    #[serde(with = "field_as_string")]
+    pub amm: Pubkey,
+    pub label: String,
+    #[serde(with = "field_as_string")]
+    pub in_amount: u64,
+    #[serde(with = "field_as_string")]
+    pub out_amount: u64,
+}
+
+impl QuoteResponse {
+    /// Output guaranteed after slippage: for ExactIn the `out_amount` reduced by `slippage_bps`
+    /// rounded down, for ExactOut the exact `out_amount`
+    pub fn minimum_out_amount(&self) -> u64 {
+        match self.swap_mode {
+            SwapMode::ExactIn => apply_slippage(
+                self.out_amount,
+                self.slippage_bps,
+                SlippageDirection::MinimumOut,
+            ),
+            SwapMode::ExactOut => self.out_amount,
+        }
+    }
+
+    /// Input that may be spent after slippage: for ExactOut the `in_amount` increased by `slippage_bps`
+    /// rounded up, for ExactIn the exact `in_amount`
+    pub fn maximum_in_amount(&self) -> u64 {
+        match self.swap_mode {
+            SwapMode::ExactIn => self.in_amount,
+            SwapMode::ExactOut => apply_slippage(
+                self.in_amount,
+                self.slippage_bps,
+                SlippageDirection::MaximumIn,
+            ),
+        }
+    }
+
+    /// Worst-case amount lost to slippage, in raw units: for ExactIn the output shortfall
+    /// `out_amount - minimum_out_amount()`, for ExactOut the extra input `maximum_in_amount() - in_amount`
+    pub fn max_slippage_loss(&self) -> u64 {
+        match self.swap_mode {
+            SwapMode::ExactIn => self.out_amount - self.minimum_out_amount(),
+            SwapMode::ExactOut => self.maximum_in_amount() - self.in_amount,
+        }
+    }
+
+    /// Output actually received after the platform fee, which is `out_amount` in both swap modes. For
+    /// ExactIn the fee is taken from the output and `out_amount` is already net of it, the route's
+    /// last step yielding `out_amount + platform_fee.amount`. For ExactOut the fee is charged on the
+    /// input, leaving the exact output untouched.
+    pub fn net_out_amount(&self) -> u64 {
+        self.out_amount
+    }
+
+    /// Output guaranteed after both slippage and the platform fee. For ExactIn the fee is taken from
+    /// the output before slippage applies, `out_amount` being already net of it, so this is
+    /// [`Self::minimum_out_amount`], matching the `other_amount_threshold` enforced on chain. For
+    /// ExactOut the fee is charged on the input, leaving the exact `out_amount`.
+    pub fn effective_minimum_out(&self) -> u64 {
+        match self.swap_mode {
+            SwapMode::ExactIn => self.minimum_out_amount(),
+            SwapMode::ExactOut => self.out_amount,
+        }
+    }
+
+    fn platform_fee_amount(&self) -> u64 {
+        self.platform_fee
+            .as_ref()
+            .map_or(0, |platform_fee| platform_fee.amount)
+    }
+
+    /// Worst-case rate after slippage and platform fee, in UI amounts.
+    ///
+    /// For ExactIn, the minimum output tokens received per input token, the platform fee being
+    /// taken from the output and already excluded from `out_amount`. For ExactOut, the maximum input
+    /// tokens spent per output token, the platform fee being charged on the input and already
+    /// included in `in_amount`. `None` when either decimals exceeds 28.
+    pub fn worst_case_rate(&self, in_decimals: u8, out_decimals: u8) -> Option<Decimal> {
+        let (numerator, denominator) = match self.swap_mode {
+            SwapMode::ExactIn => (
+                base_to_ui_amount(self.effective_minimum_out(), out_decimals)?,
+                base_to_ui_amount(self.in_amount, in_decimals)?,
+            ),
+            SwapMode::ExactOut => (
+                base_to_ui_amount(self.maximum_in_amount(), in_decimals)?,
+                base_to_ui_amount(self.out_amount, out_decimals)?,
+            ),
+        };
+        Some(numerator.checked_div(denominator).unwrap_or_default())
+    }
+
+    /// Normalize the quote into an order on a `base/quote` market, in UI amounts, to compare it with
+    /// order book venues. The exactly specified token is the base: ExactIn sells the input as base
+    /// for the output as quote, ExactOut buys the output as base with the input as quote. In both
+    /// cases the platform fee is charged in the quote token and the quote size is already net of it,
+    /// so `fee` is informative and must not be deducted again. `None` when either decimals exceeds 28.
+    pub fn order_summary(&self, in_decimals: u8, out_decimals: u8) -> Option<OrderSummary> {
+        let in_size = base_to_ui_amount(self.in_amount, in_decimals)?;
+        let out_size = base_to_ui_amount(self.out_amount, out_decimals)?;
+        let (side, base_size, quote_size, quote_decimals) = match self.swap_mode {
+            SwapMode::ExactIn => (OrderSide::Sell, in_size, out_size, out_decimals),
+            SwapMode::ExactOut => (OrderSide::Buy, out_size, in_size, in_decimals),
+        };
+        Some(OrderSummary {
+            side,
+            price: quote_size.checked_div(base_size).unwrap_or_default(),
+            base_size,
+            quote_size,
+            fee: base_to_ui_amount(self.platform_fee_amount(), quote_decimals)?,
+        })
+    }
+
+    /// Signed deviation, in basis points rounded half away from zero, of the quoted price from
+    /// `reference_price`, both in output tokens per input token in UI amounts. Positive when the quote
+    /// gives more output per input than the reference. Saturates at the `i32` bounds.
+    ///
+    /// Returns an `Option` rather than a bare `i32` because the deviation is undefined in two cases,
+    /// which would otherwise be indistinguishable from a real deviation: `None` when
+    /// `reference_price` is zero, and when `in_decimals` or `out_decimals` exceeds the 28 decimal
+    /// places [`Decimal`] supports.
+    pub fn price_deviation_bps(
+        &self,
+        reference_price: Decimal,
+        in_decimals: u8,
+        out_decimals: u8,
+    ) -> Option<i32> {
+        let price = base_to_ui_amount(self.out_amount, out_decimals)?
+            .checked_div(base_to_ui_amount(self.in_amount, in_decimals)?)
+            .unwrap_or_default();
+        let deviation = (price - reference_price).checked_div(reference_price)?;
+        (deviation * Decimal::from(10_000))
+            .round_dp_with_strategy(0, RoundingStrategy::MidpointAwayFromZero)
+            .clamp(Decimal::from(i32::MIN), Decimal::from(i32::MAX))
+            .to_i32()
+    }
+
+    /// `price_impact_pct` rounded with `rounding` to exactly `scale` decimal places, at most 28,
+    /// padding with trailing zeros so values display and store at a fixed scale. Use
+    /// [`RoundingMode::Ceil`] to never understate the impact, [`RoundingMode::HalfUp`] for display.
+    pub fn price_impact_rounded(&self, scale: u32, rounding: RoundingMode) -> Decimal {
+        let mut rounded = self
+            .price_impact_pct
+            .round_dp_with_strategy(scale, rounding.strategy());
+        rounded.rescale(scale);
+        rounded
+    }
+
+    /// Whether the computed auto slippage reached `requested_max`, the `max_auto_slippage_bps` of the
+    /// request, meaning the suggested slippage was likely capped below what the API would have picked
+    pub fn auto_slippage_clamped(&self, requested_max: u16) -> bool {
+        self.computed_auto_slippage
+            .is_some_and(|computed_auto_slippage| computed_auto_slippage >= requested_max)
+    }
+
+    /// Whether the quote was computed with a larger probe amount to minimize slippage,
+    /// see [`Self::uses_quote_minimizing_slippage`]
+    pub fn minimized_slippage(&self) -> bool {
+        self.uses_quote_minimizing_slippage.unwrap_or(false)
+    }
+
+    /// Whether any step of the route swaps from or into `mint`
+    pub fn touches_mint(&self, mint: &Pubkey) -> bool {
+        self.route_plan
+            .iter()
+            .any(|step| step.swap_info.input_mint == *mint || step.swap_info.output_mint == *mint)
+    }
+
+    /// Whether the route uses `mint` as an intermediate token, other than the quote's input or output mint
+    pub fn routes_through_mint(&self, mint: &Pubkey) -> bool {
+        *mint != self.input_mint && *mint != self.output_mint && self.touches_mint(mint)
+    }
+
+    /// Whether the input, output or any intermediate mint is in `known_fee_mints`, in which case the amount
+    /// actually received can be lower than `out_amount`. The API does not report transfer fees, so detection
+    /// relies entirely on the caller's list of fee-on-transfer mints
+    pub fn may_have_transfer_fee(&self, known_fee_mints: &HashSet<Pubkey>) -> bool {
+        known_fee_mints.contains(&self.input_mint)
+            || known_fee_mints.contains(&self.output_mint)
+            || known_fee_mints.iter().any(|mint| self.touches_mint(mint))
+    }
+
+    /// Amount the route was searched with when the quote minimized slippage. The public API does not
+    /// currently report it, so this is `None` unless the endpoint sends `probeAmount`. Without it,
+    /// `uses_quote_minimizing_slippage` remains the only signal that a probe was used.
+    pub fn used_probe_amount(&self) -> Option<u64> {
+        self.probe_amount
+            .filter(|_| self.uses_quote_minimizing_slippage == Some(true))
+    }
+
+    /// Deserialize `value`, failing with [`ClientError::UnknownFields`] listing the paths of fields
+    /// that are not modeled, including those kept in [`SwapInfo::extra`]
+    pub fn from_json_strict(value: Value) -> Result<Self, ClientError> {
+        let quote_response: Self = serde_json::from_value(value.clone())
+            .map_err(|error| safe_u64::with_amount_path(error, &value))?;
+        let mut unknown_fields = Vec::new();
+        collect_unknown_fields(
+            &value,
+            &serde_json::to_value(&quote_response)?,
+            "",
+            &mut unknown_fields,
+        );
+        for (index, step) in quote_response.route_plan.iter().enumerate() {
+            unknown_fields.extend(
+                step.swap_info
+                    .extra
+                    .keys()
+                    .map(|key| format!("routePlan[{index}].swapInfo.{key}")),
+            );
+        }
+        if !unknown_fields.is_empty() {
+            unknown_fields.sort();
+            return Err(ClientError::UnknownFields(unknown_fields));
+        }
+        Ok(quote_response)
+    }
+
+    /// `time_taken` as a [`Duration`], zero when the reported value is negative or not a number
+    pub fn server_duration(&self) -> Duration {
+        Duration::try_from_secs_f64(self.time_taken).unwrap_or_default()
+    }
+
+    /// Dex labels of the route steps, in route order
+    pub fn route_labels(&self) -> Vec<&str> {
+        self.route_plan
+            .iter()
+            .map(|step| step.swap_info.label.as_str())
+            .collect()
+    }
+
+    /// Whether every step of the route uses one of the `allowed` dex labels
+    pub fn uses_only_dexes(&self, allowed: &[&str]) -> bool {
+        self.route_labels()
+            .iter()
+            .all(|label| allowed.contains(label))
+    }
+
+    /// How `other` differs from this quote, amounts being `other` minus `self`
+    pub fn diff(&self, other: &QuoteResponse) -> QuoteDiff {
+        QuoteDiff {
+            in_amount: i128::from(other.in_amount) - i128::from(self.in_amount),
+            out_amount: i128::from(other.out_amount) - i128::from(self.out_amount),
+            price_impact_pct: other.price_impact_pct - self.price_impact_pct,
+            context_slot: i128::from(other.context_slot) - i128::from(self.context_slot),
+            route_changed: self.route_labels() != other.route_labels(),
+        }
+    }
+
+    /// The route plan as [`CompactHop`]s in route order, dropping fees, percents and AMM specific
+    /// fields, e.g. for logging or storing many quotes
+    pub fn route_plan_compact(&self) -> Vec<CompactHop> {
+        self.route_plan
+            .iter()
+            .map(|step| CompactHop {
+                amm: step.swap_info.amm_key,
+                label: step.swap_info.label.clone(),
+                in_amount: step.swap_info.in_amount,
+                out_amount: step.swap_info.out_amount,
+            })
+            .collect()
+    }
+}
+
+/// Differences between two quotes for the same request, see [`QuoteResponse::diff`]
+#[derive(Clone, Debug, PartialEq)]
+pub struct QuoteDiff {
+    pub in_amount: i128,
+    pub out_amount: i128,
+    pub price_impact_pct: Decimal,
+    pub context_slot: i128,
+    /// Whether the routes go through a different sequence of dexes
+    pub route_changed: bool,
+}
+
+/// Contiguous span of quoted amounts served by the same route, see [`route_ranges`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RouteRange {
+    /// Dex labels of the route steps, in route order
+    pub route: Vec<String>,
+    /// Smallest and largest exactly specified amount, the input for ExactIn and the output for
+    /// ExactOut, among the quotes of the span
+    pub min_amount: u64,
+    pub max_amount: u64,
+    pub quotes: usize,
+}
+
+/// Group quotes of the same pair at various sizes into spans of amounts served by the same route,
+/// ordered by amount, to reveal at which sizes the chosen route changes. A route showing up again
+/// after another one forms a new span.
+pub fn route_ranges(quotes: &[QuoteResponse]) -> Vec<RouteRange> {
+    let specified_amount = |quote: &QuoteResponse| match quote.swap_mode {
+        SwapMode::ExactIn => quote.in_amount,
+        SwapMode::ExactOut => quote.out_amount,
+    };
+    let mut quotes: Vec<&QuoteResponse> = quotes.iter().collect();
+    quotes.sort_by_key(|quote| specified_amount(quote));
+    let mut ranges: Vec<RouteRange> = Vec::new();
+    for quote in quotes {
+        let amount = specified_amount(quote);
+        let route = quote.route_labels();
+        match ranges.last_mut() {
+            Some(range) if range.route == route => {
+                range.max_amount = amount;
+                range.quotes += 1;
+            }
+            _ => ranges.push(RouteRange {
+                route: route.into_iter().map(String::from).collect(),
+                min_amount: amount,
+                max_amount: amount,
+                quotes: 1,
+            }),
+        }
+    }
+    ranges
+}
+
+/// The same pair and amount quoted from both sides: spending exactly `amount` of the input,
+/// and receiving exactly `amount` of the output
+#[derive(Clone, Debug)]
+pub struct BothModesQuote {
+    pub exact_in: QuoteResponse,
+    pub exact_out: QuoteResponse,
+}
+
+/// The same request quoted without and with `minimize_slippage`
+#[derive(Clone, Debug)]
+pub struct MinimizeSlippageComparison {
+    pub plain: QuoteResponse,
+    pub minimized: QuoteResponse,
+    /// `out_amount` of the minimized quote minus that of the plain one, positive when minimizing helped
+    pub out_amount_delta: i128,
+}
+
+/// Quote of one candidate output mint, see [`CandidateQuotes`]
+#[derive(Clone, Debug)]
+pub struct CandidateQuote {
+    pub output_mint: Pubkey,
+    pub quote_response: QuoteResponse,
+    /// `out_amount` in UI terms, using the decimals of `output_mint`
+    pub ui_out_amount: Decimal,
+}
+
+/// Successful candidate quotes of [`JupiterSwapApiClient::quote_candidates`](crate::JupiterSwapApiClient::quote_candidates)
+#[derive(Clone, Debug)]
+pub struct CandidateQuotes {
+    /// Sorted by decreasing `ui_out_amount`, never empty
+    pub candidates: Vec<CandidateQuote>,
+    /// How far the second best candidate trails the best one, in basis points of the best
+    /// `ui_out_amount` rounded down. `None` with a single candidate.
+    pub spread_bps: Option<u32>,
+}
+
+impl CandidateQuotes {
+    pub fn best(&self) -> &CandidateQuote {
+        &self.candidates[0]
+    }
+}
+
+/// Quote paired with the time it was fetched at, to expire quotes by age
+#[derive(Clone, Debug)]
+pub struct TimedQuote {
+    pub response: QuoteResponse,
+    pub fetched_at: Instant,
+    /// Server time from the response `Date` header, with one second resolution, to reconcile
+    /// against slots independently of the local clock
+    pub server_time: Option<SystemTime>,
+}
+
+impl TimedQuote {
+    /// Age in server time: elapsed since `server_time`, the server's `Date` for the response, so the
+    /// time spent in transit counts too. Falls back to the local `fetched_at` without the header.
+    /// Never less than the time elapsed since `fetched_at`, so a local clock behind the server's
+    /// cannot make a quote look fresher than it is.
+    pub fn age(&self) -> Duration {
+        let local_age = self.fetched_at.elapsed();
+        self.server_time
+            .and_then(|server_time| SystemTime::now().duration_since(server_time).ok())
+            .map_or(local_age, |server_age| server_age.max(local_age))
+    }
+
+    pub fn is_expired(&self, max_age: Duration) -> bool {
+        self.age() > max_age
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    fn quote_json() -> Value {
+        serde_json::from_str(include_str!("../tests/fixtures/quote_exact_in.json")).unwrap()
+    }
+
+    /// ExactIn quote with `platformFeeBps=20`, whose `outAmount` is net of the platform fee
+    fn platform_fee_quote() -> QuoteResponse {
+        serde_json::from_str(include_str!(
+            "../tests/fixtures/quote_exact_in_platform_fee.json"
+        ))
+        .unwrap()
+    }
+
+    /// Checks `preset` displays and serializes as `value`, and that the builder sends it as
+    /// `routingConstraints` without touching other routing parameters
+    fn assert_routing_preset(preset: RoutingConstraintPreset, value: &str) {
+        assert_eq!(preset.to_string(), value);
+        assert_eq!(serde_json::to_value(preset).unwrap(), json!(value));
+        assert_eq!(
+            serde_json::from_value::<RoutingConstraintPreset>(json!(value)).unwrap(),
+            preset
+        );
+
+        let quote_request = QuoteRequest::builder(Pubkey::new_unique(), Pubkey::new_unique(), 1)
+            .only_direct_routes(true)
+            .max_accounts(20)
+            .routing_preset(preset)
+            .build();
+        assert_eq!(quote_request.routing_constraints.as_deref(), Some(value));
+        assert_eq!(quote_request.only_direct_routes, Some(true));
+        assert_eq!(quote_request.max_accounts, Some(20));
+        let query = serde_qs::to_string(&InternalQuoteRequest::from(quote_request)).unwrap();
+        assert!(query.contains(&format!("routingConstraints={value}")));
+    }
+
+    #[test]
+    fn best_price_preset_is_sent_as_routing_constraints() {
+        assert_routing_preset(RoutingConstraintPreset::BestPrice, "bestPrice");
+    }
+
+    #[test]
+    fn low_latency_preset_is_sent_as_routing_constraints() {
+        assert_routing_preset(RoutingConstraintPreset::LowLatency, "lowLatency");
+    }
+
+    #[test]
+    fn minimal_accounts_preset_is_sent_as_routing_constraints() {
+        assert_routing_preset(RoutingConstraintPreset::MinimalAccounts, "minimalAccounts");
+    }
+
+    #[test]
+    fn net_out_amount_does_not_deduct_the_fee_twice() {
+        let quote_response = platform_fee_quote();
+        let route_out_amount = quote_response
+            .route_plan
+            .last()
+            .unwrap()
+            .swap_info
+            .out_amount;
+        let platform_fee = quote_response.platform_fee.as_ref().unwrap();
+        assert_eq!(
+            route_out_amount - platform_fee.amount,
+            quote_response.out_amount
+        );
+        assert_eq!(quote_response.net_out_amount(), 145_016_611);
+    }
+
+    #[test]
+    fn effective_minimum_out_applies_slippage_to_the_net_output() {
+        let quote_response = platform_fee_quote();
+        assert_eq!(quote_response.effective_minimum_out(), 144_291_527);
+        assert_eq!(
+            quote_response.effective_minimum_out(),
+            quote_response.other_amount_threshold
+        );
+    }
+
+    /// [`platform_fee_quote`] turned into an ExactOut quote, the fee being charged on the input
+    fn exact_out_platform_fee_quote() -> QuoteResponse {
+        QuoteResponse {
+            swap_mode: SwapMode::ExactOut,
+            other_amount_threshold: 1_005_000_000,
+            platform_fee: Some(PlatformFee {
+                amount: 2_000_000,
+                fee_bps: 20,
+            }),
+            ..platform_fee_quote()
+        }
+    }
+
+    #[test]
+    fn worst_case_rate_exact_in_with_platform_fee() {
+        let rate = platform_fee_quote().worst_case_rate(9, 6).unwrap();
+        assert_eq!(rate, Decimal::new(144_291_527, 6));
+    }
+
+    #[test]
+    fn worst_case_rate_exact_out_with_platform_fee() {
+        let rate = exact_out_platform_fee_quote()
+            .worst_case_rate(9, 6)
+            .unwrap();
+        assert_eq!(
+            rate,
+            Decimal::new(1_005_000_000, 9) / Decimal::new(145_016_611, 6)
+        );
+    }
+
+    #[test]
+    fn worst_case_rate_rejects_unsupported_decimals() {
+        assert_eq!(platform_fee_quote().worst_case_rate(29, 6), None);
+    }
+
+    #[test]
+    fn order_summary_reports_the_fee_without_deducting_it() {
+        let order_summary = platform_fee_quote().order_summary(9, 6).unwrap();
+        assert_eq!(
+            order_summary,
+            OrderSummary {
+                side: OrderSide::Sell,
+                price: Decimal::new(145_016_611, 6),
+                base_size: Decimal::ONE,
+                quote_size: Decimal::new(145_016_611, 6),
+                fee: Decimal::new(290_614, 6),
+            }
+        );
+
+        let order_summary = exact_out_platform_fee_quote().order_summary(9, 6).unwrap();
+        assert_eq!(order_summary.side, OrderSide::Buy);
+        assert_eq!(order_summary.base_size, Decimal::new(145_016_611, 6));
+        assert_eq!(order_summary.quote_size, Decimal::ONE);
+        assert_eq!(
+            order_summary.fee,
+            Decimal::new(2, 0) / Decimal::new(1_000, 0)
+        );
+    }
+
+    #[test]
+    fn quote_args_match_manual_construction() {
+        let quote_args: HashMap<String, String> = QuoteArgs::new()
+            .raw("first", "1")
+            .raw("second", "two")
+            .raw("first", "one")
+            .into();
+        let manual = HashMap::from([
+            ("first".to_string(), "one".to_string()),
+            ("second".to_string(), "two".to_string()),
+        ]);
+        assert_eq!(quote_args, manual);
+
+        let quote_request = QuoteRequest::builder(Pubkey::default(), Pubkey::default(), 1)
+            .quote_args(QuoteArgs::new().raw("first", "one").raw("second", "two"))
+            .build();
+        assert_eq!(quote_request.quote_args, Some(manual));
+    }
+
+    #[test]
+    fn used_probe_amount_when_reported() {
+        let mut value = quote_json();
+        value["usesQuoteMinimizingSlippage"] = json!(true);
+        value["probeAmount"] = json!("5000000000");
+        let quote_response: QuoteResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(quote_response.used_probe_amount(), Some(5_000_000_000));
+    }
+
+    #[test]
+    fn used_probe_amount_when_absent_or_unused() {
+        let mut value = quote_json();
+        value["usesQuoteMinimizingSlippage"] = json!(true);
+        let quote_response: QuoteResponse = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(quote_response.probe_amount, None);
+        assert_eq!(quote_response.used_probe_amount(), None);
+
+        value["usesQuoteMinimizingSlippage"] = json!(false);
+        value["probeAmount"] = json!("5000000000");
+        let quote_response: QuoteResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(quote_response.used_probe_amount(), None);
+    }
+
+    #[test]
+    fn net_out_amount_without_platform_fee() {
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        assert_eq!(quote_response.net_out_amount(), 145_307_225);
+    }
+
+    #[test]
+    fn deserializes_applied_compute_unit_score() {
+        let mut value = quote_json();
+        value["appliedComputeUnitScore"] = json!({ "maxPenaltyBps": 25.0 });
+        let quote_response: QuoteResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(
+            quote_response.applied_compute_unit_score,
+            Some(AppliedComputeUnitScore {
+                max_penalty_bps: Some(25.0)
+            })
+        );
+    }
+
+    #[test]
+    fn from_json_strict_accepts_modeled_fields() {
+        let mut value = quote_json();
+        value["appliedComputeUnitScore"] = json!({ "maxPenaltyBps": 25.0 });
+        assert!(QuoteResponse::from_json_strict(value).is_ok());
+    }
+
+    #[test]
+    fn from_json_strict_rejects_unknown_fields() {
+        let mut value = quote_json();
+        value["newTopLevelField"] = json!(1);
+        value["routePlan"][0]["swapInfo"]["newSwapInfoField"] = json!("x");
+        match QuoteResponse::from_json_strict(value) {
+            Err(ClientError::UnknownFields(fields)) => assert_eq!(
+                fields,
+                ["newTopLevelField", "routePlan[0].swapInfo.newSwapInfoField"]
+            ),
+            other => panic!("expected unknown fields, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn from_json_strict_ignores_unknown_null_fields() {
+        let mut value = quote_json();
+        value["newTopLevelField"] = Value::Null;
+        assert!(QuoteResponse::from_json_strict(value).is_ok());
+    }
+
+    #[test]
+    fn route_plan_compact_keeps_hop_order_and_amounts() {
+        let mut value = quote_json();
+        let mut second_hop = value["routePlan"][0].clone();
+        second_hop["swapInfo"]["label"] = json!("Meteora");
+        second_hop["swapInfo"]["inAmount"] = json!("145307225");
+        second_hop["swapInfo"]["outAmount"] = json!("999");
+        second_hop["swapInfo"]["ammSpecificField"] = json!(1);
+        value["routePlan"] = json!([value["routePlan"][0], second_hop]);
+        let quote_response: QuoteResponse = serde_json::from_value(value).unwrap();
+
+        let hops = quote_response.route_plan_compact();
+
+        let first_amm = quote_response.route_plan[0].swap_info.amm_key;
+        assert_eq!(
+            hops,
+            [
+                CompactHop {
+                    amm: first_amm,
+                    label: "Orca V2".to_string(),
+                    in_amount: 1_000_000_000,
+                    out_amount: 145_307_225,
+                },
+                CompactHop {
+                    amm: first_amm,
+                    label: "Meteora".to_string(),
+                    in_amount: 145_307_225,
+                    out_amount: 999,
+                },
+            ]
+        );
+        assert_eq!(
+            serde_json::to_value(&hops[1]).unwrap(),
+            json!({
+                "amm": first_amm.to_string(),
+                "label": "Meteora",
+                "inAmount": "145307225",
+                "outAmount": "999",
+            })
+        );
+    }
+
+    #[test]
+    fn price_deviation_bps_is_positive_above_the_reference() {
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        // 145.307225 USDC per SOL, 21.19 bps above 145
+        assert_eq!(
+            quote_response.price_deviation_bps(Decimal::from(145), 9, 6),
+            Some(21)
+        );
+    }
+
+    #[test]
+    fn price_deviation_bps_is_negative_below_the_reference() {
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        // 312.85 bps below 150
+        assert_eq!(
+            quote_response.price_deviation_bps(Decimal::from(150), 9, 6),
+            Some(-313)
+        );
+        assert_eq!(
+            quote_response.price_deviation_bps(Decimal::new(145_307_225, 6), 9, 6),
+            Some(0)
+        );
+    }
+
+    #[test]
+    fn price_deviation_bps_is_none_when_undefined() {
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        assert_eq!(
+            quote_response.price_deviation_bps(Decimal::ZERO, 9, 6),
+            None
+        );
+        assert_eq!(
+            quote_response.price_deviation_bps(Decimal::from(145), 29, 6),
+            None
+        );
+    }
+
+    #[test]
+    fn to_curl_carries_the_query_params() {
+        let input_mint = Pubkey::new_unique();
+        let output_mint = Pubkey::new_unique();
+        let quote_request = QuoteRequest::builder(input_mint, output_mint, 1_000_000)
+            .slippage_bps(50)
+            .only_direct_routes(true)
+            .build()
+            .with_quote_arg("customArg", "1");
+
+        let curl = quote_request
+            .to_curl("https://api.jup.ag/swap/v1/")
+            .unwrap();
+
+        assert!(
+            curl.starts_with(&format!(
+                "curl -X GET 'https://api.jup.ag/swap/v1/quote?inputMint={input_mint}\
+                 &outputMint={output_mint}&amount=1000000&slippageBps=50"
+            )),
+            "{curl}"
+        );
+        assert!(curl.contains("&onlyDirectRoutes=true"), "{curl}");
+        assert!(curl.contains("&customArg=1'"), "{curl}");
+        assert!(curl.contains("'x-api-key: <redacted>'"), "{curl}");
+    }
+
+    fn price_impact_rounded(price_impact_pct: &str, scale: u32, rounding: RoundingMode) -> String {
+        let mut value = quote_json();
+        value["priceImpactPct"] = json!(price_impact_pct);
+        let quote_response: QuoteResponse = serde_json::from_value(value).unwrap();
+        quote_response
+            .price_impact_rounded(scale, rounding)
+            .to_string()
+    }
+
+    #[test]
+    fn price_impact_rounded_applies_the_rounding_mode() {
+        use RoundingMode::{Ceil, Floor, HalfUp};
+        for (price_impact_pct, scale, floor, ceil, half_up) in [
+            ("0.12345", 4, "0.1234", "0.1235", "0.1235"),
+            ("0.12344", 4, "0.1234", "0.1235", "0.1234"),
+            ("0.0001", 2, "0.00", "0.01", "0.00"),
+            ("1.5", 0, "1", "2", "2"),
+            ("-0.125", 2, "-0.13", "-0.12", "-0.13"),
+        ] {
+            assert_eq!(price_impact_rounded(price_impact_pct, scale, Floor), floor);
+            assert_eq!(price_impact_rounded(price_impact_pct, scale, Ceil), ceil);
+            assert_eq!(
+                price_impact_rounded(price_impact_pct, scale, HalfUp),
+                half_up
+            );
+        }
+    }
+
+    #[test]
+    fn price_impact_rounded_pads_trailing_zeros() {
+        for rounding in [
+            RoundingMode::Floor,
+            RoundingMode::Ceil,
+            RoundingMode::HalfUp,
+        ] {
+            assert_eq!(price_impact_rounded("0.0001", 6, rounding), "0.000100");
+            assert_eq!(price_impact_rounded("0", 3, rounding), "0.000");
+            assert_eq!(price_impact_rounded("12.5", 4, rounding), "12.5000");
+        }
+    }
+
+    #[test]
+    fn quote_args_accumulate_one_after_the_other() {
+        let mut quote_request = QuoteRequest::default()
+            .with_quote_arg("first", "1")
+            .with_quote_arg("second", "2");
+        assert_eq!(
+            quote_request.quote_args,
+            Some(HashMap::from([
+                ("first".to_string(), "1".to_string()),
+                ("second".to_string(), "2".to_string()),
+            ]))
+        );
+
+        quote_request.set_quote_arg("first", "3");
+        quote_request.set_quote_arg("third", "4");
+        assert_eq!(
+            quote_request.quote_args,
+            Some(HashMap::from([
+                ("first".to_string(), "3".to_string()),
+                ("second".to_string(), "2".to_string()),
+                ("third".to_string(), "4".to_string()),
+            ]))
+        );
+    }
+
+    #[test]
+    fn safe_defaults_restrict_intermediate_tokens_and_set_slippage() {
+        let input_mint = Pubkey::new_unique();
+        let output_mint = Pubkey::new_unique();
+        let quote_request = QuoteRequest::builder(input_mint, output_mint, 1_000)
+            .safe_defaults()
+            .build();
+        assert_eq!(quote_request.restrict_intermediate_tokens, Some(true));
+        assert_eq!(quote_request.slippage_bps, SAFE_DEFAULT_SLIPPAGE_BPS);
+        assert_eq!(
+            serde_json::to_value(&quote_request).unwrap(),
+            serde_json::to_value(QuoteRequest {
+                input_mint,
+                output_mint,
+                amount: 1_000,
+                restrict_intermediate_tokens: Some(true),
+                slippage_bps: SAFE_DEFAULT_SLIPPAGE_BPS,
+                ..QuoteRequest::default()
+            })
+            .unwrap()
+        );
+
+        let query = serde_qs::to_string(&InternalQuoteRequest::from(quote_request)).unwrap();
+        assert!(query.contains("restrictIntermediateTokens=true"));
+        assert!(query.contains(&format!("slippageBps={SAFE_DEFAULT_SLIPPAGE_BPS}")));
+    }
+
+    #[test]
+    fn minimized_slippage_defaults_to_false_when_absent() {
+        let mut value = quote_json();
+        value
+            .as_object_mut()
+            .unwrap()
+            .remove("usesQuoteMinimizingSlippage");
+        let quote_response: QuoteResponse = serde_json::from_value(value.clone()).unwrap();
+        assert!(!quote_response.minimized_slippage());
+
+        value["usesQuoteMinimizingSlippage"] = json!(false);
+        let quote_response: QuoteResponse = serde_json::from_value(value.clone()).unwrap();
+        assert!(!quote_response.minimized_slippage());
+
+        value["usesQuoteMinimizingSlippage"] = json!(true);
+        let quote_response: QuoteResponse = serde_json::from_value(value).unwrap();
+        assert!(quote_response.minimized_slippage());
+    }
+
+    #[test]
+    fn may_have_transfer_fee_with_a_flagged_mint() {
+        let quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        assert!(!quote_response.may_have_transfer_fee(&HashSet::from([Pubkey::new_unique()])));
+        assert!(quote_response.may_have_transfer_fee(&HashSet::from([quote_response.output_mint])));
+
+        let fee_mint = Pubkey::new_unique();
+        let mut two_hops = quote_response.clone();
+        let mut second_step = two_hops.route_plan[0].clone();
+        two_hops.route_plan[0].swap_info.output_mint = fee_mint;
+        second_step.swap_info.input_mint = fee_mint;
+        two_hops.route_plan.push(second_step);
+        assert!(!quote_response.may_have_transfer_fee(&HashSet::from([fee_mint])));
+        assert!(two_hops.may_have_transfer_fee(&HashSet::from([fee_mint])));
+    }
+
+    #[test]
+    fn unmodeled_swap_info_fields_survive_a_round_trip() {
+        let mut value = quote_json();
+        value["routePlan"][0]["swapInfo"]["poolVersion"] = json!(2);
+        value["routePlan"][0]["swapInfo"]["tickArrays"] = json!(["a", "b"]);
+        let quote_response: QuoteResponse = serde_json::from_value(value.clone()).unwrap();
+        let extra = &quote_response.route_plan[0].swap_info.extra;
+        assert_eq!(extra.get("poolVersion"), Some(&json!(2)));
+        assert_eq!(extra.get("tickArrays"), Some(&json!(["a", "b"])));
+
+        let round_trip = serde_json::to_value(&quote_response).unwrap();
+        assert_eq!(
+            round_trip["routePlan"][0]["swapInfo"],
+            value["routePlan"][0]["swapInfo"]
+        );
+    }
+
+    #[test]
+    fn server_duration_converts_time_taken_seconds() {
+        let mut quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        assert_eq!(quote_response.server_duration(), Duration::from_millis(3));
+
+        quote_response.time_taken = 1.25;
+        assert_eq!(
+            quote_response.server_duration(),
+            Duration::from_millis(1_250)
+        );
+
+        quote_response.time_taken = -1.0;
+        assert_eq!(quote_response.server_duration(), Duration::ZERO);
+        quote_response.time_taken = f64::NAN;
+        assert_eq!(quote_response.server_duration(), Duration::ZERO);
+    }
+
+    #[test]
+    fn auto_slippage_clamped_at_the_requested_maximum() {
+        let mut value = quote_json();
+        value["computedAutoSlippage"] = json!(300);
+        let clamped: QuoteResponse = serde_json::from_value(value.clone()).unwrap();
+        assert!(clamped.auto_slippage_clamped(300));
+        assert!(clamped.auto_slippage_clamped(250));
+
+        value["computedAutoSlippage"] = json!(120);
+        let unclamped: QuoteResponse = serde_json::from_value(value).unwrap();
+        assert!(!unclamped.auto_slippage_clamped(300));
+
+        let without_auto_slippage: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        assert!(!without_auto_slippage.auto_slippage_clamped(300));
+    }
+
+    #[test]
+    fn exclude_category_expands_into_dex_labels() {
+        let quote_request = QuoteRequest::builder(Pubkey::new_unique(), Pubkey::new_unique(), 1)
+            .excluded_dexes("Phoenix, Lifinity V2".to_string())
+            .exclude_category(DexCategory::OrderBook)
+            .exclude_category(DexCategory::Stable)
+            .build();
+        assert_eq!(
+            quote_request.excluded_dexes.as_deref(),
+            Some("Phoenix,Lifinity V2,OpenBook V2,Saber,Mercurial,Stabble Stable Swap")
+        );
+
+        let quote_request = QuoteRequest::builder(Pubkey::new_unique(), Pubkey::new_unique(), 1)
+            .exclude_category(DexCategory::Clmm)
+            .build();
+        assert_eq!(
+            quote_request.excluded_dexes.as_deref(),
+            Some("Whirlpool,Raydium CLMM,Meteora DLMM,Invariant,Crema")
+        );
+    }
+
+    #[test]
+    fn max_slippage_loss_in_each_swap_mode() {
+        let exact_in: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        // 145_307_225 * 0.995 = 144_580_688.875, rounded down
+        assert_eq!(exact_in.minimum_out_amount(), 144_580_688);
+        assert_eq!(exact_in.max_slippage_loss(), 726_537);
+
+        let exact_out = QuoteResponse {
+            swap_mode: SwapMode::ExactOut,
+            in_amount: 999_999_999,
+            ..exact_in
+        };
+        // 999_999_999 * 1.005 = 1_004_999_998.995, rounded up
+        assert_eq!(exact_out.maximum_in_amount(), 1_004_999_999);
+        assert_eq!(exact_out.max_slippage_loss(), 5_000_000);
+    }
+
+    #[test]
+    fn uses_only_dexes_rejects_an_unexpected_dex() {
+        let mut quote_response: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        assert!(quote_response.uses_only_dexes(&["Orca V2", "Whirlpool"]));
+
+        let mut second_step = quote_response.route_plan[0].clone();
+        second_step.swap_info.label = "Unknown AMM".to_string();
+        quote_response.route_plan.push(second_step);
+        assert!(!quote_response.uses_only_dexes(&["Orca V2", "Whirlpool"]));
+        assert!(quote_response.uses_only_dexes(&["Orca V2", "Unknown AMM"]));
+    }
+
+    #[test]
+    fn route_ranges_switch_routes_at_a_size_threshold() {
+        let base: QuoteResponse = serde_json::from_value(quote_json()).unwrap();
+        let quote_of = |in_amount: u64, label: &str| {
+            let mut quote_response = QuoteResponse {
+                in_amount,
+                ..base.clone()
+            };
+            quote_response.route_plan[0].swap_info.label = label.to_string();
+            quote_response
+        };
+        // Given out of order, small sizes go through Orca V2 and larger ones from 10 SOL through Whirlpool
+        let quotes = [
+            quote_of(50_000_000_000, "Whirlpool"),
+            quote_of(1_000_000_000, "Orca V2"),
+            quote_of(10_000_000_000, "Whirlpool"),
+            quote_of(5_000_000_000, "Orca V2"),
+        ];
+        assert_eq!(
+            route_ranges(&quotes),
+            vec![
+                RouteRange {
+                    route: vec!["Orca V2".to_string()],
+                    min_amount: 1_000_000_000,
+                    max_amount: 5_000_000_000,
+                    quotes: 2,
+                },
+                RouteRange {
+                    route: vec!["Whirlpool".to_string()],
+                    min_amount: 10_000_000_000,
+                    max_amount: 50_000_000_000,
+                    quotes: 2,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn deprecation_warnings_list_each_deprecated_param() {
+        assert!(QuoteRequest::default().deprecation_warnings().is_empty());
+        // An explicit `false` is still sent, so it is reported too
+        let quote_request = QuoteRequest {
+            auto_slippage: Some(false),
+            ..QuoteRequest::default()
+        };
+        assert_eq!(quote_request.deprecation_warnings().len(), 1);
+
+        let quote_request = QuoteRequest {
+            auto_slippage: Some(true),
+            max_auto_slippage_bps: Some(300),
+            compute_auto_slippage: true,
+            auto_slippage_collision_usd_value: Some(1_000),
+            ..QuoteRequest::default()
+        };
+        let warnings = quote_request.deprecation_warnings();
+        assert_eq!(warnings.len(), 4, "{warnings:?}");
+        for (warning, param) in warnings.iter().zip([
+            "auto_slippage ",
+            "max_auto_slippage_bps ",
+            "compute_auto_slippage ",
+            "auto_slippage_collision_usd_value ",
+        ]) {
+            assert!(warning.starts_with(param), "{warning}");
+            assert!(warning.contains("dynamic_slippage"), "{warning}");
+        }
+
+        let quote_request = QuoteRequest {
+            max_auto_slippage_bps: Some(300),
+            ..QuoteRequest::default()
+        };
+        assert_eq!(
+            quote_request.deprecation_warnings(),
+            ["max_auto_slippage_bps is deprecated, set dynamic_slippage in the TransactionConfig of the swap instead with its max_bps"]
+        );
+    }
+}
@@ -0,0 +1,33 @@
+//! Rate limit status reported by the API through `X-RateLimit-*` headers
+//!
+
+use reqwest::header::HeaderMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RateLimitStatus {
+    /// Requests allowed in the current window
+    pub limit: Option<u64>,
+    /// Requests left in the current window
+    pub remaining: Option<u64>,
+    /// Raw `X-RateLimit-Reset` value, as sent by the server
+    pub reset: Option<u64>,
+}
+
+impl RateLimitStatus {
+    /// `None` when the response carries none of the rate limit headers
+    pub fn from_headers(headers: &HeaderMap) -> Option<Self> {
+        let parse = |name: &str| {
+            headers
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.trim().parse().ok())
+        };
+        let status = Self {
+            limit: parse("x-ratelimit-limit"),
+            remaining: parse("x-ratelimit-remaining"),
+            reset: parse("x-ratelimit-reset"),
+        };
+        (status.limit.is_some() || status.remaining.is_some() || status.reset.is_some())
+            .then_some(status)
+    }
+}
@@ -0,0 +1,56 @@
+//! Referral fee account derivation
+//!
+
+use solana_sdk::{pubkey, pubkey::Pubkey};
+
+pub const REFERRAL_PROGRAM_ID: Pubkey = pubkey!("REFER4ZgmyYx9c6He5XfaTMiGfdLwRnkV4RPp9t9iF3");
+
+/// Derive the fee token account of `referral_account` for `mint`,
+/// usable as `TransactionConfig::fee_account`
+pub fn derive_referral_fee_account(referral_account: Pubkey, mint: Pubkey) -> Pubkey {
+    derive_referral_fee_account_with_program_id(referral_account, mint, &REFERRAL_PROGRAM_ID)
+}
+
+/// Same as [`derive_referral_fee_account`], for deployments of the referral program
+/// at another address than [`REFERRAL_PROGRAM_ID`]
+pub fn derive_referral_fee_account_with_program_id(
+    referral_account: Pubkey,
+    mint: Pubkey,
+    referral_program_id: &Pubkey,
+) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"referral_ata", referral_account.as_ref(), mint.as_ref()],
+        referral_program_id,
+    )
+    .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REFERRAL_ACCOUNT: Pubkey = pubkey!("US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx");
+    const USDC_MINT: Pubkey = pubkey!("EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v");
+
+    /// Expected addresses were derived outside this crate from the same seeds
+    #[test]
+    fn derives_the_referral_fee_account_of_a_known_vector() {
+        assert_eq!(
+            derive_referral_fee_account(REFERRAL_ACCOUNT, USDC_MINT),
+            pubkey!("GhF4rZUh8JXCWp1KZvBd7bD95iJ2PQzh2L64P4B5cKNw")
+        );
+    }
+
+    #[test]
+    fn derives_the_referral_fee_account_under_a_custom_program() {
+        let referral_program_id = pubkey!("cGfHiC6Kgg3FpFZvgwGcswsCRtp4aBP2fzuXRQPizuN");
+        assert_eq!(
+            derive_referral_fee_account_with_program_id(
+                REFERRAL_ACCOUNT,
+                USDC_MINT,
+                &referral_program_id
+            ),
+            pubkey!("RxuhYhzBVoj4Z2uBNkNrzeufjSAYU5Eq5Q5PUPbs7wT")
+        );
+    }
+}
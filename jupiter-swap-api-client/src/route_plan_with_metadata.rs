@@ -1,4 +1,8 @@
+use std::collections::{HashMap, HashSet};
+
+use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
 
 use crate::quote::SwapInfo;
 
@@ -9,6 +13,503 @@ pub type RoutePlanWithMetadata = Vec<RoutePlanStep>;
 #[serde(rename_all = "camelCase")]
 pub struct RoutePlanStep {
     pub swap_info: SwapInfo,
+    /// Share of the split level's input routed through this step
     pub percent: u8,
 }
 
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum RouteComplexity {
+    /// A single step swapping the input straight into the output
+    Direct,
+    /// Sequential steps without splitting, with the number of hops
+    MultiHop(usize),
+    /// At least one split level, with the total number of legs
+    Split(usize),
+}
+
+/// Helpers over [`RoutePlanWithMetadata`], which is a plain `Vec` and cannot carry inherent methods
+pub trait RoutePlanWithMetadataExt {
+    fn leg_percentages(&self) -> Vec<u8>;
+
+    /// Steps sharing an input mint form a split level, whose percentages must sum to 100
+    fn percentages_valid(&self) -> bool;
+
+    fn complexity(&self) -> RouteComplexity;
+
+    /// AMM keys used by more than one step, in order of first use. Reusing a pool within a route
+    /// is usually suboptimal or a sign of a malformed route.
+    fn duplicate_amms(&self) -> Vec<Pubkey>;
+
+    /// `out_amount / in_amount` of each step in raw units, ignoring decimals, so only meaningful
+    /// relative to the rate of the same pair elsewhere. Steps with a zero input yield zero.
+    fn hop_rates(&self) -> Vec<Decimal>;
+
+    /// Whether every input and output mint along the route is in `stable_mints`
+    fn is_stable_only(&self, stable_mints: &HashSet<Pubkey>) -> bool;
+
+    /// The leg with the largest estimated impact, as a share of what it swaps. A leg's impact adds its
+    /// fee, `fee_amount` relative to its amount in `fee_mint`, to how far its [`Self::hop_rates`] rate
+    /// trails the best rate between the same two mints through the route's other legs, chaining hops
+    /// where needed. Raw rates of unrelated pairs are not comparable without decimals and prices, so the
+    /// hops of a purely sequential route are ranked by their fees. `None` when no leg has an impact.
+    fn worst_leg(&self) -> Option<&SwapInfo>;
+
+    /// Graphviz DOT rendering, with one node per mint and one edge per step labeled with the dex,
+    /// the step's percent and its raw amounts. Legs of a split level show up as parallel edges.
+    fn to_dot(&self) -> String;
+
+    /// Share of the route's volume, in percent, handled by each dex label. Within a split level, the
+    /// steps sharing an input mint, every step weighs its `in_amount`. Amounts of different mints are
+    /// not comparable without prices, so each level counts equally and the shares sum to 100. Levels
+    /// without any input are ignored, and the result is empty when no level has any.
+    fn volume_share_by_dex(&self) -> HashMap<String, Decimal>;
+}
+
+impl RoutePlanWithMetadataExt for RoutePlanWithMetadata {
+    fn leg_percentages(&self) -> Vec<u8> {
+        self.iter().map(|step| step.percent).collect()
+    }
+
+    fn percentages_valid(&self) -> bool {
+        let mut split_levels: Vec<(Pubkey, u32)> = Vec::new();
+        for step in self {
+            let input_mint = step.swap_info.input_mint;
+            match split_levels
+                .iter_mut()
+                .find(|(mint, _)| *mint == input_mint)
+            {
+                Some((_, total)) => *total += u32::from(step.percent),
+                None => split_levels.push((input_mint, u32::from(step.percent))),
+            }
+        }
+        split_levels.iter().all(|(_, total)| *total == 100)
+    }
+
+    fn complexity(&self) -> RouteComplexity {
+        if self.iter().any(|step| step.percent < 100) {
+            RouteComplexity::Split(self.len())
+        } else if self.len() > 1 {
+            RouteComplexity::MultiHop(self.len())
+        } else {
+            RouteComplexity::Direct
+        }
+    }
+
+    fn duplicate_amms(&self) -> Vec<Pubkey> {
+        let mut duplicates = Vec::new();
+        for (i, step) in self.iter().enumerate() {
+            let amm_key = step.swap_info.amm_key;
+            if !duplicates.contains(&amm_key)
+                && self[i + 1..]
+                    .iter()
+                    .any(|other| other.swap_info.amm_key == amm_key)
+            {
+                duplicates.push(amm_key);
+            }
+        }
+        duplicates
+    }
+
+    fn hop_rates(&self) -> Vec<Decimal> {
+        self.iter()
+            .map(|step| {
+                Decimal::from(step.swap_info.out_amount)
+                    .checked_div(Decimal::from(step.swap_info.in_amount))
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+
+    fn is_stable_only(&self, stable_mints: &HashSet<Pubkey>) -> bool {
+        self.iter().all(|step| {
+            stable_mints.contains(&step.swap_info.input_mint)
+                && stable_mints.contains(&step.swap_info.output_mint)
+        })
+    }
+
+    fn worst_leg(&self) -> Option<&SwapInfo> {
+        let rates = self.hop_rates();
+        self.iter()
+            .zip(&rates)
+            .enumerate()
+            .filter_map(|(i, (step, rate))| {
+                let swap_info = &step.swap_info;
+                let shortfall = best_path_rate(
+                    self,
+                    &rates,
+                    i,
+                    swap_info.input_mint,
+                    swap_info.output_mint,
+                    &mut Vec::new(),
+                )
+                .and_then(|best_rate| (best_rate - rate).checked_div(best_rate))
+                .filter(|shortfall| *shortfall > Decimal::ZERO)
+                .unwrap_or_default();
+                let impact = fee_share(swap_info) + shortfall;
+                (impact > Decimal::ZERO).then_some((swap_info, impact))
+            })
+            .max_by_key(|(_, impact)| *impact)
+            .map(|(swap_info, _)| swap_info)
+    }
+
+    fn to_dot(&self) -> String {
+        let mut mints = Vec::new();
+        for step in self {
+            for mint in [step.swap_info.input_mint, step.swap_info.output_mint] {
+                if !mints.contains(&mint) {
+                    mints.push(mint);
+                }
+            }
+        }
+        let mut dot = String::from("digraph route {\n");
+        for mint in &mints {
+            dot.push_str(&format!("    \"{mint}\";\n"));
+        }
+        for step in self {
+            let swap_info = &step.swap_info;
+            dot.push_str(&format!(
+                "    \"{}\" -> \"{}\" [label=\"{} {}%\\n{} -> {}\"];\n",
+                swap_info.input_mint,
+                swap_info.output_mint,
+                swap_info.label.replace('\\', "\\\\").replace('"', "\\\""),
+                step.percent,
+                swap_info.in_amount,
+                swap_info.out_amount,
+            ));
+        }
+        dot.push('}');
+        dot
+    }
+
+    fn volume_share_by_dex(&self) -> HashMap<String, Decimal> {
+        let mut split_levels: Vec<(Pubkey, u128)> = Vec::new();
+        for step in self {
+            let input_mint = step.swap_info.input_mint;
+            let in_amount = u128::from(step.swap_info.in_amount);
+            match split_levels
+                .iter_mut()
+                .find(|(mint, _)| *mint == input_mint)
+            {
+                Some((_, total)) => *total += in_amount,
+                None => split_levels.push((input_mint, in_amount)),
+            }
+        }
+        split_levels.retain(|(_, total)| *total > 0);
+        let mut shares: HashMap<String, Decimal> = HashMap::new();
+        let level_share = Decimal::ONE_HUNDRED / Decimal::from(split_levels.len().max(1));
+        for step in self {
+            let Some((_, total)) = split_levels
+                .iter()
+                .find(|(mint, _)| *mint == step.swap_info.input_mint)
+            else {
+                continue;
+            };
+            *shares.entry(step.swap_info.label.clone()).or_default() +=
+                level_share * Decimal::from(step.swap_info.in_amount) / Decimal::from(*total);
+        }
+        shares
+    }
+}
+
+/// `fee_amount` relative to the leg's gross amount in `fee_mint`, zero when the fee is unknown or
+/// charged in a third mint
+fn fee_share(swap_info: &SwapInfo) -> Decimal {
+    let (Some(fee_amount), Some(fee_mint)) = (swap_info.fee_amount, swap_info.fee_mint) else {
+        return Decimal::ZERO;
+    };
+    let gross_amount = if fee_mint == swap_info.input_mint {
+        swap_info.in_amount
+    } else if fee_mint == swap_info.output_mint {
+        swap_info.out_amount.saturating_add(fee_amount)
+    } else {
+        return Decimal::ZERO;
+    };
+    Decimal::from(fee_amount)
+        .checked_div(Decimal::from(gross_amount))
+        .unwrap_or_default()
+}
+
+/// Best rate from `from` to `to` chaining the route's steps other than `excluded`, never revisiting
+/// a mint. `None` when no such path exists.
+fn best_path_rate(
+    route: &RoutePlanWithMetadata,
+    rates: &[Decimal],
+    excluded: usize,
+    from: Pubkey,
+    to: Pubkey,
+    visited: &mut Vec<Pubkey>,
+) -> Option<Decimal> {
+    visited.push(from);
+    let mut best: Option<Decimal> = None;
+    for (i, (step, rate)) in route.iter().zip(rates).enumerate() {
+        let swap_info = &step.swap_info;
+        if i == excluded || swap_info.input_mint != from || visited.contains(&swap_info.output_mint)
+        {
+            continue;
+        }
+        let path_rate = if swap_info.output_mint == to {
+            Some(*rate)
+        } else {
+            best_path_rate(route, rates, excluded, swap_info.output_mint, to, visited)
+                .and_then(|rest| rest.checked_mul(*rate))
+        };
+        best = best.max(path_rate);
+    }
+    visited.pop();
+    best
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn step(
+        label: &str,
+        input_mint: Pubkey,
+        output_mint: Pubkey,
+        in_amount: u64,
+        out_amount: u64,
+        fee_amount: Option<u64>,
+        percent: u8,
+    ) -> RoutePlanStep {
+        RoutePlanStep {
+            swap_info: SwapInfo {
+                amm_key: Pubkey::new_unique(),
+                label: label.to_string(),
+                input_mint,
+                output_mint,
+                in_amount,
+                out_amount,
+                fee_amount,
+                fee_mint: fee_amount.map(|_| input_mint),
+                extra: HashMap::new(),
+            },
+            percent,
+        }
+    }
+
+    #[test]
+    fn worst_leg_ranks_sequential_hops_by_fee() {
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let route = vec![
+            step(
+                "Orca",
+                sol,
+                usdc,
+                1_000_000_000,
+                145_000_000,
+                Some(2_500_000),
+                100,
+            ),
+            step(
+                "Meteora",
+                usdc,
+                bonk,
+                145_000_000,
+                6_000_000_000,
+                Some(4_350_000),
+                100,
+            ),
+        ];
+
+        assert_eq!(route.worst_leg().unwrap().label, "Meteora");
+    }
+
+    #[test]
+    fn worst_leg_finds_the_trailing_split_leg() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let route = vec![
+            step("Orca", sol, usdc, 600_000_000, 87_000_000, None, 60),
+            step("Raydium", sol, usdc, 400_000_000, 52_000_000, None, 40),
+        ];
+
+        assert_eq!(route.worst_leg().unwrap().label, "Raydium");
+    }
+
+    #[test]
+    fn worst_leg_compares_a_direct_leg_with_a_two_hop_path() {
+        let (sol, usdt, usdc) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let route = vec![
+            step("Whirlpool", sol, usdt, 500_000_000, 72_500_000, None, 50),
+            step("Stable", usdt, usdc, 72_500_000, 72_500_000, None, 100),
+            step("Lifinity", sol, usdc, 500_000_000, 70_000_000, None, 50),
+        ];
+
+        assert_eq!(route.worst_leg().unwrap().label, "Lifinity");
+    }
+
+    #[test]
+    fn worst_leg_is_none_without_any_impact() {
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let route = vec![
+            step("Orca", sol, usdc, 1_000_000_000, 145_000_000, None, 100),
+            step("Meteora", usdc, bonk, 145_000_000, 6_000_000_000, None, 100),
+        ];
+
+        assert_eq!(route.worst_leg(), None);
+    }
+
+    #[test]
+    fn volume_share_by_dex_weighs_split_legs_by_amount() {
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        // Percents are rounded by the API, amounts are not
+        let route = vec![
+            step("Orca", sol, usdc, 333_333_333, 48_333_333, None, 33),
+            step("Raydium", sol, usdc, 333_333_333, 48_333_333, None, 33),
+            step("Meteora", sol, usdc, 333_333_334, 48_333_334, None, 34),
+            step("Orca", usdc, bonk, 145_000_000, 6_000_000_000, None, 100),
+        ];
+
+        let shares = route.volume_share_by_dex();
+
+        let total: Decimal = shares.values().sum();
+        assert!(
+            (total - Decimal::ONE_HUNDRED).abs() < Decimal::new(1, 20),
+            "{total}"
+        );
+        let rounded = |label: &str| shares[label].round_dp(4);
+        assert_eq!(rounded("Orca"), Decimal::new(666_667, 4));
+        assert_eq!(rounded("Raydium"), Decimal::new(166_667, 4));
+        assert_eq!(rounded("Meteora"), Decimal::new(166_667, 4));
+    }
+
+    #[test]
+    fn volume_share_by_dex_is_empty_without_volume() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        assert!(RoutePlanWithMetadata::new()
+            .volume_share_by_dex()
+            .is_empty());
+        let route = vec![step("Orca", sol, usdc, 0, 0, None, 100)];
+        assert!(route.volume_share_by_dex().is_empty());
+    }
+
+    #[test]
+    fn sixty_forty_split_is_valid() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut route = vec![
+            step("Orca", sol, usdc, 600, 87_000, None, 60),
+            step("Raydium", sol, usdc, 400, 58_000, None, 40),
+        ];
+        assert_eq!(route.leg_percentages(), vec![60, 40]);
+        assert!(route.percentages_valid());
+
+        route[1].percent = 30;
+        assert_eq!(route.leg_percentages(), vec![60, 30]);
+        assert!(!route.percentages_valid());
+    }
+
+    #[test]
+    fn complexity_of_direct_multi_hop_and_split_routes() {
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let direct = vec![step("Orca", sol, usdc, 1_000, 145_000, None, 100)];
+        assert_eq!(direct.complexity(), RouteComplexity::Direct);
+
+        let two_hops = vec![
+            step("Orca", sol, bonk, 1_000, 9_000_000, None, 100),
+            step("Raydium", bonk, usdc, 9_000_000, 144_000, None, 100),
+        ];
+        assert_eq!(two_hops.complexity(), RouteComplexity::MultiHop(2));
+
+        let split = vec![
+            step("Orca", sol, usdc, 600, 87_000, None, 60),
+            step("Raydium", sol, usdc, 400, 58_000, None, 40),
+        ];
+        assert_eq!(split.complexity(), RouteComplexity::Split(2));
+    }
+
+    #[test]
+    fn duplicate_amms_reports_a_reused_pool_once() {
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let mut route = vec![
+            step("Orca", sol, bonk, 1_000, 9_000_000, None, 100),
+            step("Raydium", bonk, usdc, 9_000_000, 144_000, None, 100),
+            step("Orca", usdc, sol, 144_000, 990, None, 100),
+        ];
+        assert!(route.duplicate_amms().is_empty());
+
+        let reused = route[0].swap_info.amm_key;
+        route[2].swap_info.amm_key = reused;
+        assert_eq!(route.duplicate_amms(), vec![reused]);
+        route.push(route[0].clone());
+        assert_eq!(route.duplicate_amms(), vec![reused]);
+    }
+
+    #[test]
+    fn hop_rates_divide_each_output_by_its_input() {
+        let (sol, usdc, bonk) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let route = vec![
+            step("Orca", sol, bonk, 1_000, 9_000_000, None, 100),
+            step("Raydium", bonk, usdc, 9_000_000, 144_000, None, 100),
+        ];
+        assert_eq!(
+            route.hop_rates(),
+            vec![Decimal::from(9_000), Decimal::new(16, 3)]
+        );
+
+        let dry = vec![step("Orca", sol, usdc, 0, 0, None, 100)];
+        assert_eq!(dry.hop_rates(), vec![Decimal::ZERO]);
+    }
+
+    #[test]
+    fn is_stable_only_rejects_a_non_stable_mint() {
+        let (usdc, usdt, sol) = (
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        );
+        let stable_mints = HashSet::from([usdc, usdt]);
+        let stable = vec![step("Orca", usdc, usdt, 1_000, 999, None, 100)];
+        assert!(stable.is_stable_only(&stable_mints));
+
+        let through_sol = vec![
+            step("Orca", usdc, sol, 1_000, 6, None, 100),
+            step("Raydium", sol, usdt, 6, 998, None, 100),
+        ];
+        assert!(!through_sol.is_stable_only(&stable_mints));
+    }
+
+    #[test]
+    fn to_dot_draws_split_legs_as_parallel_edges() {
+        let (sol, usdc) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let route = vec![
+            step("Orca", sol, usdc, 600, 87_000, None, 60),
+            step("Raydium \"CP\"", sol, usdc, 400, 58_000, None, 40),
+        ];
+        assert_eq!(
+            route.to_dot(),
+            format!(
+                "digraph route {{\n    \"{sol}\";\n    \"{usdc}\";\n    \
+                 \"{sol}\" -> \"{usdc}\" [label=\"Orca 60%\\n600 -> 87000\"];\n    \
+                 \"{sol}\" -> \"{usdc}\" [label=\"Raydium \\\"CP\\\" 40%\\n400 -> 58000\"];\n}}"
+            )
+        );
+    }
+}
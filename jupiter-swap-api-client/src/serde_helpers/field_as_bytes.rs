@@ -0,0 +1,90 @@
+//! Pubkeys as their raw 32 bytes, for binary protocols where the base58 string of
+//! [`field_as_string`](super::field_as_string), used for the API, is wasteful
+//!
+
+use {
+    serde::{Deserialize, Deserializer, Serialize, Serializer},
+    solana_sdk::pubkey::Pubkey,
+};
+
+pub fn serialize<S>(pubkey: &Pubkey, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    pubkey.to_bytes().serialize(serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<Pubkey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    <[u8; 32]>::deserialize(deserializer).map(Pubkey::new_from_array)
+}
+
+/// Pubkey serialized as its raw 32 bytes wherever it appears, for collections and nested types
+/// `#[serde(with = "field_as_bytes")]` cannot reach, such as `Vec<PubkeyBytes>`
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Hash, Clone, Copy)]
+pub struct PubkeyBytes(#[serde(with = "self")] pub Pubkey);
+
+impl From<Pubkey> for PubkeyBytes {
+    fn from(pubkey: Pubkey) -> Self {
+        Self(pubkey)
+    }
+}
+
+impl From<PubkeyBytes> for Pubkey {
+    fn from(pubkey: PubkeyBytes) -> Self {
+        pubkey.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::PubkeyBytes;
+    use crate::serde_helpers::field_as_string;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Keys {
+        #[serde(with = "field_as_string")]
+        base58: Pubkey,
+        #[serde(with = "super")]
+        raw: Pubkey,
+        many: Vec<PubkeyBytes>,
+    }
+
+    fn keys() -> Keys {
+        Keys {
+            base58: Pubkey::new_unique(),
+            raw: Pubkey::new_unique(),
+            many: vec![Pubkey::new_unique().into(), Pubkey::new_unique().into()],
+        }
+    }
+
+    #[test]
+    fn json_round_trips_both_encodings() {
+        let keys = keys();
+        let value = serde_json::to_value(&keys).unwrap();
+        assert_eq!(value["base58"], json!(keys.base58.to_string()));
+        assert_eq!(value["raw"], json!(keys.raw.to_bytes()));
+        assert_eq!(value["many"][1], json!(keys.many[1].0.to_bytes()));
+        assert_eq!(serde_json::from_value::<Keys>(value).unwrap(), keys);
+    }
+
+    #[test]
+    fn bincode_round_trips_both_encodings() {
+        let keys = keys();
+        let bytes = bincode::serialize(&keys).unwrap();
+        let base58_len = keys.base58.to_string().len();
+        // Length-prefixed base58 string, 32 raw bytes, then the length-prefixed raw keys
+        assert_eq!(bytes.len(), 8 + base58_len + 32 + 8 + 2 * 32);
+        assert_eq!(
+            &bytes[8 + base58_len..8 + base58_len + 32],
+            keys.raw.as_ref()
+        );
+        assert_eq!(bincode::deserialize::<Keys>(&bytes).unwrap(), keys);
+    }
+}
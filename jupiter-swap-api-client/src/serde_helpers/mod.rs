@@ -1,2 +1,5 @@
+pub mod field_as_bytes;
 pub mod field_as_string;
 pub mod option_field_as_string;
+pub mod safe_u64;
+pub mod saturating_u64;
@@ -0,0 +1,133 @@
+//! Token amounts as decimal strings, rejecting values beyond `u64::MAX` with an explicit error
+//!
+
+use {
+    serde::{de, Deserialize, Deserializer, Serialize, Serializer},
+    serde_json::Value,
+    std::num::IntErrorKind,
+};
+
+pub fn serialize<S>(amount: &u64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    amount.to_string().serialize(serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    s.parse()
+        .map_err(|e: std::num::ParseIntError| match e.kind() {
+            IntErrorKind::PosOverflow => de::Error::custom(format!("amount {s} exceeds u64 range")),
+            _ => de::Error::custom(format!("invalid amount {s:?}: {e}")),
+        })
+}
+
+/// Prefix an error of [`deserialize`] with the path of the rejected amount within `value`, such as
+/// `routePlan[0].swapInfo.outAmount: amount 18446744073709551616 exceeds u64 range`, since serde_json
+/// only reports a line and column, and nothing at all when deserializing from a [`Value`]. Other
+/// errors, and amounts that cannot be located, are returned unchanged.
+pub(crate) fn with_amount_path(error: serde_json::Error, value: &Value) -> serde_json::Error {
+    if !error.is_data() {
+        return error;
+    }
+    let message = error.to_string();
+    match rejected_amount(&message).and_then(|amount| find_string(value, &amount, "")) {
+        Some(path) => de::Error::custom(format!("{path}: {message}")),
+        None => error,
+    }
+}
+
+/// The amount string quoted in an error message of [`deserialize`]
+fn rejected_amount(message: &str) -> Option<String> {
+    if let Some(rest) = message.strip_prefix("amount ") {
+        let (amount, _) = rest.split_once(" exceeds u64 range")?;
+        return Some(amount.to_string());
+    }
+    let (quoted, _) = message.strip_prefix("invalid amount ")?.rsplit_once(": ")?;
+    serde_json::from_str(quoted).ok()
+}
+
+/// Path of the first string equal to `needle`, with object keys joined by `.` and array
+/// indices in brackets
+fn find_string(value: &Value, needle: &str, path: &str) -> Option<String> {
+    match value {
+        Value::String(s) if s == needle => Some(path.to_string()),
+        Value::Array(items) => items
+            .iter()
+            .enumerate()
+            .find_map(|(index, item)| find_string(item, needle, &format!("{path}[{index}]"))),
+        Value::Object(map) => map.iter().find_map(|(key, item)| {
+            let path = if path.is_empty() {
+                key.clone()
+            } else {
+                format!("{path}.{key}")
+            };
+            find_string(item, needle, &path)
+        }),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+    use serde_json::json;
+
+    use std::collections::HashMap;
+
+    use super::with_amount_path;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Amount(#[serde(with = "super")] u64);
+
+    fn parse(json: &str) -> Result<u64, String> {
+        serde_json::from_str::<Amount>(json)
+            .map(|Amount(amount)| amount)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn round_trips_the_full_range() {
+        for amount in [0, 1, u64::MAX] {
+            let json = serde_json::to_string(&Amount(amount)).unwrap();
+            assert_eq!(json, format!("\"{amount}\""));
+            assert_eq!(parse(&json), Ok(amount));
+        }
+    }
+
+    #[test]
+    fn rejects_amounts_beyond_u64() {
+        let error = parse("\"18446744073709551616\"").unwrap_err();
+        assert!(error.contains("exceeds u64 range"), "{error}");
+    }
+
+    #[test]
+    fn rejects_malformed_amounts() {
+        for json in ["\"\"", "\"-1\"", "\"1.5\"", "\"abc\""] {
+            let error = parse(json).unwrap_err();
+            assert!(error.contains("invalid amount"), "{error}");
+        }
+        assert!(parse("1").is_err());
+    }
+
+    #[test]
+    fn with_amount_path_names_the_rejected_field() {
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Step {
+            #[serde(rename = "outAmount", with = "super")]
+            out_amount: u64,
+        }
+        let value = json!({ "steps": [{ "outAmount": "1" }, { "outAmount": "-5" }] });
+        let error =
+            serde_json::from_value::<HashMap<String, Vec<Step>>>(value.clone()).unwrap_err();
+        assert_eq!(
+            with_amount_path(error, &value).to_string(),
+            "steps[1].outAmount: invalid amount \"-5\": invalid digit found in string"
+        );
+    }
+}
@@ -0,0 +1,65 @@
+//! Token amounts as decimal strings, saturating at `u64::MAX`, for display only contexts
+//! where an out of range amount should not fail the whole response
+//!
+
+use {
+    serde::{de, Deserialize, Deserializer, Serializer},
+    std::num::IntErrorKind,
+};
+
+pub fn serialize<S>(amount: &u64, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    super::safe_u64::serialize(amount, serializer)
+}
+
+pub fn deserialize<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s: String = String::deserialize(deserializer)?;
+    match s.parse() {
+        Ok(amount) => Ok(amount),
+        Err(e) if *e.kind() == IntErrorKind::PosOverflow => Ok(u64::MAX),
+        Err(e) => Err(de::Error::custom(format!("invalid amount {s:?}: {e}"))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::{Deserialize, Serialize};
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Amount(#[serde(with = "super")] u64);
+
+    fn parse(json: &str) -> Result<u64, String> {
+        serde_json::from_str::<Amount>(json)
+            .map(|Amount(amount)| amount)
+            .map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn round_trips_the_full_range() {
+        for amount in [0, 1, u64::MAX] {
+            let json = serde_json::to_string(&Amount(amount)).unwrap();
+            assert_eq!(json, format!("\"{amount}\""));
+            assert_eq!(parse(&json), Ok(amount));
+        }
+    }
+
+    #[test]
+    fn saturates_amounts_beyond_u64() {
+        assert_eq!(parse("\"18446744073709551616\""), Ok(u64::MAX));
+        assert_eq!(parse(&format!("\"{}\"", u128::MAX)), Ok(u64::MAX));
+    }
+
+    #[test]
+    fn rejects_malformed_amounts() {
+        for json in ["\"\"", "\"-1\"", "\"1.5\"", "\"abc\""] {
+            let error = parse(json).unwrap_err();
+            assert!(error.contains("invalid amount"), "{error}");
+        }
+        assert!(parse("1").is_err());
+    }
+}
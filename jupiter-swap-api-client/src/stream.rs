@@ -0,0 +1,429 @@
+//! Polling quote streams and paged token listings
+//!
+
+use std::time::Duration;
+
+use futures::{future, stream, Stream, StreamExt, TryStreamExt};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::{
+    check_status_code_and_deserialize, join_url,
+    quote::{QuoteRequest, QuoteResponse},
+    token::TokenInfo,
+    ClientError, JupiterSwapApiClient,
+};
+
+/// Tokens requested per page by [`JupiterSwapApiClient::tokens_all`]
+pub const TOKENS_PAGE_SIZE: usize = 1_000;
+
+/// Wait before requesting the next token page once the rate limit reports no request left
+pub const TOKENS_PAGE_RATE_LIMIT_DELAY: Duration = Duration::from_secs(1);
+
+/// Change between two consecutive quotes of a stream, see [`JupiterSwapApiClient::quote_stream_events`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuoteEvent {
+    /// The sequence of dex labels along the route changed
+    RouteChanged { from: Vec<String>, to: Vec<String> },
+    /// The quoted output amount changed
+    PriceChanged { from: u64, to: u64 },
+    /// The quote was computed at a later slot
+    SlotAdvanced { from: u64, to: u64 },
+}
+
+fn quote_events(previous: &QuoteResponse, current: &QuoteResponse) -> Vec<QuoteEvent> {
+    let mut events = Vec::new();
+    let (previous_labels, current_labels) = (previous.route_labels(), current.route_labels());
+    if previous_labels != current_labels {
+        events.push(QuoteEvent::RouteChanged {
+            from: previous_labels.into_iter().map(String::from).collect(),
+            to: current_labels.into_iter().map(String::from).collect(),
+        });
+    }
+    if previous.out_amount != current.out_amount {
+        events.push(QuoteEvent::PriceChanged {
+            from: previous.out_amount,
+            to: current.out_amount,
+        });
+    }
+    if current.context_slot > previous.context_slot {
+        events.push(QuoteEvent::SlotAdvanced {
+            from: previous.context_slot,
+            to: current.context_slot,
+        });
+    }
+    events
+}
+
+/// Whether `current` differs from `last` by no more than `tolerance_bps` of `last`
+fn within_tolerance(last: u64, current: u64, tolerance_bps: u16) -> bool {
+    u128::from(last.abs_diff(current)) * 10_000 <= u128::from(last) * u128::from(tolerance_bps)
+}
+
+impl JupiterSwapApiClient {
+    fn poll_quotes(
+        &self,
+        quote_request: QuoteRequest,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<QuoteResponse, ClientError>> {
+        stream::unfold(
+            (self.clone(), quote_request, true),
+            move |(client, quote_request, first)| async move {
+                if !first {
+                    tokio::time::sleep(interval).await;
+                }
+                let result = client.quote(&quote_request).await;
+                Some((result, (client, quote_request, false)))
+            },
+        )
+    }
+
+    /// Quote repeatedly, waiting `interval` between polls, skipping quotes identical to the last one
+    /// emitted (same `out_amount` and `context_slot`). Errors are yielded and polling continues.
+    pub fn quote_stream(
+        &self,
+        quote_request: QuoteRequest,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<QuoteResponse, ClientError>> {
+        let mut last_emitted = None;
+        self.poll_quotes(quote_request, interval)
+            .filter(move |result| {
+                let emit = match result {
+                    Ok(quote_response) => {
+                        let key = Some((quote_response.out_amount, quote_response.context_slot));
+                        let changed = key != last_emitted;
+                        last_emitted = key;
+                        changed
+                    }
+                    Err(_) => true,
+                };
+                future::ready(emit)
+            })
+    }
+
+    /// Like [`Self::quote_stream`], but deduplicating on `out_amount` alone with a tolerance: quotes
+    /// whose `out_amount` is within `dedup_tolerance_bps` of the last emitted one are skipped, whatever
+    /// their `context_slot`, to cut churn on flat markets. Errors are still yielded.
+    pub fn quote_stream_with_tolerance(
+        &self,
+        quote_request: QuoteRequest,
+        interval: Duration,
+        dedup_tolerance_bps: u16,
+    ) -> impl Stream<Item = Result<QuoteResponse, ClientError>> {
+        let mut last_emitted = None;
+        self.poll_quotes(quote_request, interval)
+            .filter(move |result| {
+                let emit = match result {
+                    Ok(quote_response) => {
+                        let out_amount = quote_response.out_amount;
+                        let changed = last_emitted.is_none_or(|last| {
+                            !within_tolerance(last, out_amount, dedup_tolerance_bps)
+                        });
+                        if changed {
+                            last_emitted = Some(out_amount);
+                        }
+                        changed
+                    }
+                    Err(_) => true,
+                };
+                future::ready(emit)
+            })
+    }
+
+    /// Like [`Self::quote_stream`], but only emits quotes whose `out_amount` is strictly better
+    /// than the best seen so far. Errors are still yielded.
+    pub fn quote_stream_improving(
+        &self,
+        quote_request: QuoteRequest,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<QuoteResponse, ClientError>> {
+        let mut best_out_amount = None;
+        self.quote_stream(quote_request, interval)
+            .filter(move |result| {
+                let emit = match result {
+                    Ok(quote_response) => {
+                        let improved =
+                            best_out_amount.is_none_or(|best| quote_response.out_amount > best);
+                        if improved {
+                            best_out_amount = Some(quote_response.out_amount);
+                        }
+                        improved
+                    }
+                    Err(_) => true,
+                };
+                future::ready(emit)
+            })
+    }
+
+    /// Poll like [`Self::quote_stream`], emitting what changed between consecutive successful quotes
+    /// instead of the quotes themselves. The first quote only sets the baseline. Errors are yielded
+    /// and polling continues.
+    pub fn quote_stream_events(
+        &self,
+        quote_request: QuoteRequest,
+        interval: Duration,
+    ) -> impl Stream<Item = Result<QuoteEvent, ClientError>> {
+        let mut previous: Option<QuoteResponse> = None;
+        self.poll_quotes(quote_request, interval)
+            .flat_map(move |result| {
+                let events = match result {
+                    Ok(quote_response) => {
+                        let events = previous
+                            .as_ref()
+                            .map(|previous| quote_events(previous, &quote_response))
+                            .unwrap_or_default();
+                        previous = Some(quote_response);
+                        events.into_iter().map(Ok).collect()
+                    }
+                    Err(e) => vec![Err(e)],
+                };
+                stream::iter(events)
+            })
+    }
+
+    async fn tokens_page(&self, page: usize) -> Result<Vec<TokenInfo>, ClientError> {
+        let request_builder = self
+            .client
+            .get(join_url(&self.token_api_base_path, "all"))
+            .query(&[("page", page), ("limit", TOKENS_PAGE_SIZE)]);
+        let response = self.send(request_builder).await?;
+        check_status_code_and_deserialize(response).await
+    }
+
+    /// Every token known to the token API, paging through `all` with `page` and `limit` parameters,
+    /// [`TOKENS_PAGE_SIZE`] tokens at a time. Paging ends at the first page shorter than that or
+    /// empty, or repeating the previous page, as when the server ignores the parameters and returns
+    /// the whole list at once. Once the rate limit reports no request left, the next page waits
+    /// [`TOKENS_PAGE_RATE_LIMIT_DELAY`]. A failing page is yielded as an error and ends the stream.
+    pub fn tokens_all(&self) -> impl Stream<Item = Result<TokenInfo, ClientError>> {
+        stream::try_unfold(
+            (self.clone(), Some(1), None::<Pubkey>),
+            |(client, page, previous_first)| async move {
+                let Some(page) = page else {
+                    return Ok::<_, ClientError>(None);
+                };
+                let rate_limited = client
+                    .last_rate_limit_status()
+                    .is_some_and(|status| status.remaining == Some(0));
+                if page > 1 && rate_limited {
+                    tokio::time::sleep(TOKENS_PAGE_RATE_LIMIT_DELAY).await;
+                }
+                let tokens = client.tokens_page(page).await?;
+                let first = tokens.first().map(|token| token.address);
+                if first.is_none() || first == previous_first {
+                    return Ok(None);
+                }
+                let next_page = (tokens.len() >= TOKENS_PAGE_SIZE).then_some(page + 1);
+                let tokens = stream::iter(tokens.into_iter().map(Ok));
+                Ok(Some((tokens, (client, next_page, first))))
+            },
+        )
+        .try_flatten()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        sync::{
+            atomic::{AtomicUsize, Ordering},
+            Arc, Mutex,
+        },
+        time::Instant,
+    };
+
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::tests::{mock_server, mock_server_with_headers, quote_json};
+
+    fn tokens_json(addresses: &[Pubkey]) -> String {
+        let tokens: Vec<Value> = addresses
+            .iter()
+            .map(|address| {
+                json!({
+                    "address": address.to_string(),
+                    "name": "Token",
+                    "symbol": "TKN",
+                    "decimals": 6,
+                })
+            })
+            .collect();
+        Value::Array(tokens).to_string()
+    }
+
+    fn page_of(path: &str) -> usize {
+        path.split(['?', '&'])
+            .find_map(|param| param.strip_prefix("page="))
+            .and_then(|page| page.parse().ok())
+            .unwrap()
+    }
+
+    fn client(base_url: String) -> JupiterSwapApiClient {
+        let mut client = JupiterSwapApiClient::new(base_url.clone(), String::new()).unwrap();
+        client.token_api_base_path = base_url;
+        client
+    }
+
+    /// The fixture quote with another output, slot and dex label
+    fn quote_at(out_amount: u64, context_slot: u64, label: &str) -> Value {
+        let mut quote = quote_json();
+        quote["outAmount"] = json!(out_amount.to_string());
+        quote["contextSlot"] = json!(context_slot);
+        quote["routePlan"][0]["swapInfo"]["label"] = json!(label);
+        quote
+    }
+
+    /// A client polling `quotes` in order, the last one repeating once they run out
+    async fn quote_sequence_client(quotes: Vec<Value>) -> JupiterSwapApiClient {
+        let polls = AtomicUsize::new(0);
+        let base_url = mock_server(move |_| {
+            let poll = polls.fetch_add(1, Ordering::SeqCst);
+            (200, quotes[poll.min(quotes.len() - 1)].to_string())
+        })
+        .await;
+        JupiterSwapApiClient::new(base_url, String::new()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn quote_stream_improving_skips_declining_quotes() {
+        let client = quote_sequence_client(vec![
+            quote_at(100, 1, "Orca"),
+            quote_at(90, 2, "Orca"),
+            quote_at(95, 3, "Orca"),
+            quote_at(110, 4, "Orca"),
+        ])
+        .await;
+        let out_amounts: Vec<u64> = client
+            .quote_stream_improving(QuoteRequest::default(), Duration::ZERO)
+            .take(2)
+            .map_ok(|quote_response| quote_response.out_amount)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(out_amounts, [100, 110]);
+    }
+
+    #[tokio::test]
+    async fn tokens_all_yields_every_page() {
+        let pages: Vec<Vec<Pubkey>> = [TOKENS_PAGE_SIZE, 5]
+            .iter()
+            .map(|len| (0..*len).map(|_| Pubkey::new_unique()).collect())
+            .collect();
+        let expected: Vec<Pubkey> = pages.concat();
+        let requested_pages = Arc::new(Mutex::new(Vec::new()));
+        let base_url = mock_server({
+            let requested_pages = requested_pages.clone();
+            move |path| {
+                let page = page_of(path);
+                requested_pages.lock().unwrap().push(page);
+                assert!(path.contains(&format!("limit={TOKENS_PAGE_SIZE}")));
+                (200, tokens_json(&pages[page - 1]))
+            }
+        })
+        .await;
+        let tokens: Vec<Pubkey> = client(base_url)
+            .tokens_all()
+            .map_ok(|token| token.address)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(tokens, expected);
+        assert_eq!(*requested_pages.lock().unwrap(), [1, 2]);
+    }
+
+    #[tokio::test]
+    async fn tokens_all_stops_when_paging_is_ignored() {
+        let addresses: Vec<Pubkey> = (0..TOKENS_PAGE_SIZE)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        let body = tokens_json(&addresses);
+        let base_url = mock_server(move |_| (200, body.clone())).await;
+        let tokens: Vec<TokenInfo> = client(base_url).tokens_all().try_collect().await.unwrap();
+        assert_eq!(tokens.len(), TOKENS_PAGE_SIZE);
+    }
+
+    #[tokio::test]
+    async fn tokens_all_ends_with_a_failing_page() {
+        let first_page: Vec<Pubkey> = (0..TOKENS_PAGE_SIZE)
+            .map(|_| Pubkey::new_unique())
+            .collect();
+        let body = tokens_json(&first_page);
+        let base_url = mock_server(move |path| match page_of(path) {
+            1 => (200, body.clone()),
+            _ => (500, json!({ "error": "internal error" }).to_string()),
+        })
+        .await;
+        let results: Vec<_> = client(base_url).tokens_all().collect().await;
+        assert_eq!(results.len(), TOKENS_PAGE_SIZE + 1);
+        assert!(results[..TOKENS_PAGE_SIZE].iter().all(Result::is_ok));
+        assert!(matches!(
+            results[TOKENS_PAGE_SIZE],
+            Err(ClientError::RequestFailed { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn tokens_all_waits_between_pages_when_rate_limited() {
+        let pages: Vec<Vec<Pubkey>> = [TOKENS_PAGE_SIZE, 1]
+            .iter()
+            .map(|len| (0..*len).map(|_| Pubkey::new_unique()).collect())
+            .collect();
+        let base_url = mock_server_with_headers(&["x-ratelimit-remaining: 0"], move |path| {
+            (200, tokens_json(&pages[page_of(path) - 1]))
+        })
+        .await;
+        let started_at = Instant::now();
+        let tokens: Vec<TokenInfo> = client(base_url).tokens_all().try_collect().await.unwrap();
+        assert_eq!(tokens.len(), TOKENS_PAGE_SIZE + 1);
+        assert!(started_at.elapsed() >= TOKENS_PAGE_RATE_LIMIT_DELAY);
+    }
+
+    #[tokio::test]
+    async fn quote_stream_events_reports_a_route_change() {
+        let client = quote_sequence_client(vec![
+            quote_at(100, 1, "Orca"),
+            quote_at(100, 1, "Raydium"),
+            quote_at(105, 2, "Raydium"),
+        ])
+        .await;
+        let events: Vec<QuoteEvent> = client
+            .quote_stream_events(QuoteRequest::default(), Duration::ZERO)
+            .take(3)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(
+            events,
+            [
+                QuoteEvent::RouteChanged {
+                    from: vec!["Orca".to_string()],
+                    to: vec!["Raydium".to_string()],
+                },
+                QuoteEvent::PriceChanged { from: 100, to: 105 },
+                QuoteEvent::SlotAdvanced { from: 1, to: 2 },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn quote_stream_with_tolerance_skips_quotes_within_the_tolerance() {
+        let client = quote_sequence_client(vec![
+            quote_at(100_000, 1, "Orca"),
+            quote_at(100_100, 2, "Orca"),
+            quote_at(99_950, 3, "Orca"),
+            quote_at(100_200, 4, "Orca"),
+            quote_at(100_250, 5, "Orca"),
+            quote_at(99_000, 6, "Orca"),
+        ])
+        .await;
+        // 10 bps of 100_000 is 100, a change of exactly 100 is still within the tolerance
+        let out_amounts: Vec<u64> = client
+            .quote_stream_with_tolerance(QuoteRequest::default(), Duration::ZERO, 10)
+            .take(3)
+            .map_ok(|quote_response| quote_response.out_amount)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(out_amounts, [100_000, 100_200, 99_000]);
+    }
+}
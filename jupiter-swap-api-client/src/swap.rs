@@ -1,11 +1,28 @@
+use std::{collections::HashSet, sync::Arc};
+
 use crate::{
-    quote::QuoteResponse, serde_helpers::field_as_string, transaction_config::TransactionConfig,
+    amounts::{apply_slippage, SlippageDirection},
+    curl_command, join_url,
+    quote::{QuoteResponse, SwapMode},
+    serde_helpers::field_as_string,
+    transaction_config::{
+        ComputeUnitPriceMicroLamports, DynamicSlippageSettings, PrioritizationFeeLamports,
+        PriorityStrategy, TransactionConfig, NATIVE_MINT,
+    },
+    ClientError,
 };
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use solana_sdk::{
+    hash::{hashv, Hash},
     instruction::{AccountMeta, Instruction},
+    packet::PACKET_DATA_SIZE,
+    pubkey,
     pubkey::Pubkey,
+    signature::Signature,
+    system_instruction::SystemInstruction,
+    system_program,
+    transaction::VersionedTransaction,
 };
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -13,11 +30,165 @@ use solana_sdk::{
 pub struct SwapRequest {
     #[serde(with = "field_as_string")]
     pub user_public_key: Pubkey,
-    pub quote_response: QuoteResponse,
+    /// Shared so one quote can back swap requests for many wallets without copying its route plan
+    pub quote_response: Arc<QuoteResponse>,
     #[serde(flatten)]
     pub config: TransactionConfig,
 }
 
+impl SwapRequest {
+    /// Render a ready-to-run curl command reproducing `POST /swap`, with the API key redacted.
+    /// Fails with [`ClientError::InvalidRequest`] when the request cannot be encoded as JSON.
+    pub fn to_curl(&self, base_url: &str) -> Result<String, ClientError> {
+        let body = serde_json::to_string(self)
+            .map_err(|e| ClientError::InvalidRequest(format!("cannot encode swap body: {e}")))?;
+        Ok(curl_command(
+            "POST",
+            &join_url(base_url, "swap"),
+            Some(&body),
+        ))
+    }
+
+    pub fn builder() -> SwapRequestBuilder {
+        SwapRequestBuilder::default()
+    }
+
+    pub fn quote(&self) -> &QuoteResponse {
+        &self.quote_response
+    }
+
+    /// Extract the embedded quote from a captured `POST /swap` JSON body,
+    /// ignoring the rest of the body so unknown or invalid config fields do not get in the way
+    pub fn quote_from_body(body: &str) -> serde_json::Result<QuoteResponse> {
+        #[derive(Deserialize)]
+        #[serde(rename_all = "camelCase")]
+        struct Body {
+            quote_response: QuoteResponse,
+        }
+
+        serde_json::from_str::<Body>(body).map(|body| body.quote_response)
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct SwapRequestBuilder {
+    user_public_key: Option<Pubkey>,
+    quote_response: Option<Arc<QuoteResponse>>,
+    config: TransactionConfig,
+}
+
+impl SwapRequestBuilder {
+    /// Start from a quote shared with other requests, which is reference counted rather than copied
+    pub fn from_shared_quote(quote_response: Arc<QuoteResponse>) -> Self {
+        Self {
+            quote_response: Some(quote_response),
+            ..Self::default()
+        }
+    }
+
+    pub fn user(mut self, user_public_key: Pubkey) -> Self {
+        self.user_public_key = Some(user_public_key);
+        self
+    }
+
+    pub fn quote(mut self, quote_response: impl Into<Arc<QuoteResponse>>) -> Self {
+        self.quote_response = Some(quote_response.into());
+        self
+    }
+
+    /// Replace the whole transaction config, discarding previous config setters
+    pub fn config(mut self, config: TransactionConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    pub fn wrap_and_unwrap_sol(mut self, wrap_and_unwrap_sol: bool) -> Self {
+        self.config.wrap_and_unwrap_sol = wrap_and_unwrap_sol;
+        self
+    }
+
+    /// Wrap and unwrap SOL only when `input_mint` or `output_mint` is [`NATIVE_MINT`], the wrapped SOL mint
+    pub fn auto_wrap_sol(mut self, input_mint: Pubkey, output_mint: Pubkey) -> Self {
+        self.config.wrap_and_unwrap_sol = input_mint == NATIVE_MINT || output_mint == NATIVE_MINT;
+        self
+    }
+
+    pub fn fee_account(mut self, fee_account: Pubkey) -> Self {
+        self.config.fee_account = Some(fee_account);
+        self
+    }
+
+    pub fn destination_token_account(mut self, destination_token_account: Pubkey) -> Self {
+        self.config.destination_token_account = Some(destination_token_account);
+        self
+    }
+
+    pub fn compute_unit_price_micro_lamports(
+        mut self,
+        compute_unit_price_micro_lamports: ComputeUnitPriceMicroLamports,
+    ) -> Self {
+        self.config.compute_unit_price_micro_lamports = Some(compute_unit_price_micro_lamports);
+        self
+    }
+
+    pub fn prioritization_fee_lamports(
+        mut self,
+        prioritization_fee_lamports: PrioritizationFeeLamports,
+    ) -> Self {
+        self.config.prioritization_fee_lamports = Some(prioritization_fee_lamports);
+        self
+    }
+
+    /// Set the prioritization fee from `strategy`, clearing any compute unit price as the API
+    /// rejects requests setting both
+    pub fn priority_strategy(mut self, strategy: PriorityStrategy) -> Self {
+        self.config.compute_unit_price_micro_lamports = None;
+        self.config.prioritization_fee_lamports = Some(strategy.into());
+        self
+    }
+
+    pub fn dynamic_compute_unit_limit(mut self, dynamic_compute_unit_limit: bool) -> Self {
+        self.config.dynamic_compute_unit_limit = dynamic_compute_unit_limit;
+        self
+    }
+
+    pub fn as_legacy_transaction(mut self, as_legacy_transaction: bool) -> Self {
+        self.config.as_legacy_transaction = as_legacy_transaction;
+        self
+    }
+
+    pub fn use_shared_accounts(mut self, use_shared_accounts: bool) -> Self {
+        self.config.use_shared_accounts = Some(use_shared_accounts);
+        self
+    }
+
+    pub fn skip_user_accounts_rpc_calls(mut self, skip_user_accounts_rpc_calls: bool) -> Self {
+        self.config.skip_user_accounts_rpc_calls = skip_user_accounts_rpc_calls;
+        self
+    }
+
+    pub fn dynamic_slippage(mut self, dynamic_slippage: DynamicSlippageSettings) -> Self {
+        self.config.dynamic_slippage = Some(dynamic_slippage);
+        self
+    }
+
+    /// Fails if the user public key or the quote is missing
+    pub fn build(self) -> Result<SwapRequest, ClientError> {
+        let user_public_key = self
+            .user_public_key
+            .filter(|user_public_key| *user_public_key != Pubkey::default())
+            .ok_or_else(|| ClientError::InvalidRequest("missing user public key".to_string()))?;
+        let quote_response = self
+            .quote_response
+            .ok_or_else(|| ClientError::InvalidRequest("missing quote response".to_string()))?;
+        Ok(SwapRequest {
+            user_public_key,
+            quote_response,
+            config: self.config,
+        })
+    }
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum PrioritizationType {
@@ -43,8 +214,8 @@ pub struct DynamicSlippageReport {
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct UiSimulationError {
-    error_code: String,
-    error: String,
+    pub error_code: String,
+    pub error: String,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug)]
@@ -54,12 +225,214 @@ pub struct SwapResponse {
     pub swap_transaction: Vec<u8>,
     pub last_valid_block_height: u64,
     pub prioritization_fee_lamports: u64,
-    pub compute_unit_limit: u32,
+    /// Compute unit limit set in the transaction, computed by simulation when `dynamic_compute_unit_limit` is enabled
+    pub compute_unit_limit: Option<u32>,
     pub prioritization_type: Option<PrioritizationType>,
     pub dynamic_slippage_report: Option<DynamicSlippageReport>,
     pub simulation_error: Option<UiSimulationError>,
 }
 
+/// Mainnet Jito tip accounts, used by [`SwapResponse::jito_tip_lamports`]
+pub const JITO_TIP_ACCOUNTS: [Pubkey; 8] = [
+    pubkey!("96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5"),
+    pubkey!("HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe"),
+    pubkey!("Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY"),
+    pubkey!("ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49"),
+    pubkey!("DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh"),
+    pubkey!("ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt"),
+    pubkey!("DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL"),
+    pubkey!("3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT"),
+];
+
+/// Jupiter v6 aggregator program
+pub const JUPITER_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+
+/// Route instructions whose arguments end with
+/// `amount: u64, quoted_amount: u64, slippage_bps: u16, platform_fee_bps: u8`,
+/// along with the positions of the source mint (when present) and destination mint among their accounts
+const ROUTE_INSTRUCTIONS: [(&str, Option<usize>, usize); 4] = [
+    ("route", None, 5),
+    ("shared_accounts_route", Some(7), 8),
+    ("exact_out_route", Some(5), 6),
+    ("shared_accounts_exact_out_route", Some(7), 8),
+];
+const ROUTE_ARGS_LEN: usize = 8 + 8 + 2 + 1;
+
+struct RouteArgs {
+    amount: u64,
+    quoted_amount: u64,
+    slippage_bps: u16,
+    source_mint_position: Option<usize>,
+    destination_mint_position: usize,
+}
+
+impl RouteArgs {
+    fn parse(data: &[u8]) -> Option<Self> {
+        let discriminator = data.get(..8)?;
+        let (_, source_mint_position, destination_mint_position) =
+            ROUTE_INSTRUCTIONS.iter().find(|(name, _, _)| {
+                hashv(&[b"global:", name.as_bytes()]).as_ref()[..8] == *discriminator
+            })?;
+        let args = data.get(data.len().checked_sub(ROUTE_ARGS_LEN)?..)?;
+        Some(Self {
+            amount: u64::from_le_bytes(args[0..8].try_into().ok()?),
+            quoted_amount: u64::from_le_bytes(args[8..16].try_into().ok()?),
+            slippage_bps: u16::from_le_bytes(args[16..18].try_into().ok()?),
+            source_mint_position: *source_mint_position,
+            destination_mint_position: *destination_mint_position,
+        })
+    }
+}
+
+/// Maximum compute units a transaction may request
+pub const MAX_COMPUTE_UNIT_LIMIT: u32 = 1_400_000;
+
+impl SwapResponse {
+    /// The reported compute unit limit, or [`MAX_COMPUTE_UNIT_LIMIT`] as a conservative budget when absent
+    pub fn compute_unit_limit_or_default(&self) -> u32 {
+        self.compute_unit_limit.unwrap_or(MAX_COMPUTE_UNIT_LIMIT)
+    }
+
+    pub fn transaction(&self) -> Result<VersionedTransaction, ClientError> {
+        Ok(bincode::deserialize(&self.swap_transaction)?)
+    }
+
+    /// The transaction with its recent blockhash replaced by `blockhash`, for tighter expiry control.
+    /// Existing signatures no longer match the message and are cleared, so the transaction must be
+    /// signed after this call.
+    pub fn with_blockhash(&self, blockhash: Hash) -> Result<VersionedTransaction, ClientError> {
+        let mut transaction = self.transaction()?;
+        transaction.message.set_recent_blockhash(blockhash);
+        transaction.signatures.fill(Signature::default());
+        Ok(transaction)
+    }
+
+    /// Size of the serialized transaction as it will be sent
+    pub fn transaction_size_bytes(&self) -> usize {
+        self.swap_transaction.len()
+    }
+
+    /// Whether the transaction is larger than [`PACKET_DATA_SIZE`] once signed and would be rejected
+    pub fn exceeds_limit(&self) -> bool {
+        self.transaction_size_bytes() > PACKET_DATA_SIZE
+    }
+
+    /// Number of signatures the transaction requires, each paying the base signature fee
+    /// on top of the prioritization fee
+    pub fn required_signatures(&self) -> Result<usize, ClientError> {
+        Ok(usize::from(
+            self.transaction()?.message.header().num_required_signatures,
+        ))
+    }
+
+    /// Number of accounts loaded by the transaction, including those resolved through address lookup tables,
+    /// to compare against the `max_accounts` estimate of the quote
+    pub fn account_count(&self) -> Result<usize, ClientError> {
+        let message = self.transaction()?.message;
+        let lookup_table_accounts: usize = message
+            .address_table_lookups()
+            .unwrap_or_default()
+            .iter()
+            .map(|lookup| lookup.writable_indexes.len() + lookup.readonly_indexes.len())
+            .sum();
+        Ok(message.static_account_keys().len() + lookup_table_accounts)
+    }
+
+    /// Total lamports transferred to [`JITO_TIP_ACCOUNTS`], or `None` without a tip transfer
+    pub fn jito_tip_lamports(&self) -> Result<Option<u64>, ClientError> {
+        self.jito_tip_lamports_to(&JITO_TIP_ACCOUNTS)
+    }
+
+    /// Same as [`Self::jito_tip_lamports`] with a custom set of tip accounts. Only system transfers to
+    /// tip accounts among the static account keys are found, not those loaded from address lookup tables.
+    pub fn jito_tip_lamports_to(
+        &self,
+        tip_accounts: &[Pubkey],
+    ) -> Result<Option<u64>, ClientError> {
+        Ok(transaction_tip_lamports(&self.transaction()?, tip_accounts))
+    }
+
+    /// Check that the transaction swaps what `quote` describes, guarding against a compromised endpoint:
+    /// the Jupiter route instruction must swap the quoted amount with a minimum output (or maximum input
+    /// for ExactOut) no worse than the quote's, and name the quoted mints at their account positions.
+    ///
+    /// Mints loaded through address lookup tables cannot be resolved offline and are reported as
+    /// [`ClientError::UnverifiableTransaction`]. Token ledger routes carry no amount and are reported
+    /// as a mismatch.
+    pub fn verify_against_quote(&self, quote: &QuoteResponse) -> Result<(), ClientError> {
+        let mismatch = |reason: String| Err(ClientError::TransactionMismatch(reason));
+        let message = self.transaction()?.message;
+        let account_keys = message.static_account_keys();
+        let route_instruction = message.instructions().iter().find_map(|instruction| {
+            let program_id = account_keys.get(usize::from(instruction.program_id_index));
+            (program_id == Some(&JUPITER_PROGRAM_ID))
+                .then(|| RouteArgs::parse(&instruction.data))
+                .flatten()
+                .map(|route_args| (instruction, route_args))
+        });
+        let Some((instruction, route_args)) = route_instruction else {
+            return mismatch("no Jupiter route instruction found".to_string());
+        };
+
+        let (expected_amount, bound_ok) = match quote.swap_mode {
+            SwapMode::ExactIn => {
+                let minimum_out = apply_slippage(
+                    route_args.quoted_amount,
+                    route_args.slippage_bps,
+                    SlippageDirection::MinimumOut,
+                );
+                (quote.in_amount, minimum_out >= quote.minimum_out_amount())
+            }
+            SwapMode::ExactOut => {
+                let maximum_in = apply_slippage(
+                    route_args.quoted_amount,
+                    route_args.slippage_bps,
+                    SlippageDirection::MaximumIn,
+                );
+                (quote.out_amount, maximum_in <= quote.maximum_in_amount())
+            }
+        };
+        if route_args.amount != expected_amount {
+            return mismatch(format!(
+                "transaction swaps amount {} but the quote is for {expected_amount}",
+                route_args.amount
+            ));
+        }
+        if !bound_ok {
+            return mismatch(format!(
+                "transaction slippage bound (quoted amount {}, {} bps) is worse than the quote's",
+                route_args.quoted_amount, route_args.slippage_bps
+            ));
+        }
+
+        let mint_at = |position: usize| {
+            let index = instruction.accounts.get(position).ok_or_else(|| {
+                ClientError::TransactionMismatch(format!(
+                    "route instruction has no account at position {position}"
+                ))
+            })?;
+            account_keys.get(usize::from(*index)).copied().ok_or_else(|| {
+                ClientError::UnverifiableTransaction(format!(
+                    "route instruction mint at position {position} is loaded from an address lookup table"
+                ))
+            })
+        };
+        let mut expected_mints = vec![(route_args.destination_mint_position, quote.output_mint)];
+        if let Some(position) = route_args.source_mint_position {
+            expected_mints.push((position, quote.input_mint));
+        }
+        for (position, expected_mint) in expected_mints {
+            let mint = mint_at(position)?;
+            if mint != expected_mint {
+                return mismatch(format!(
+                    "route instruction swaps mint {mint} but the quote is for {expected_mint}"
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
 pub mod base64_serialize_deserialize {
     use base64::{engine::general_purpose::STANDARD, Engine};
     use serde::{de, Deserializer, Serializer};
@@ -100,6 +473,167 @@ pub struct SwapInstructionsResponse {
     pub simulation_error: Option<UiSimulationError>,
 }
 
+/// Total lamports system-transferred to `tip_accounts` by `transaction`, see
+/// [`SwapResponse::jito_tip_lamports_to`]
+pub(crate) fn transaction_tip_lamports(
+    transaction: &VersionedTransaction,
+    tip_accounts: &[Pubkey],
+) -> Option<u64> {
+    let message = &transaction.message;
+    let account_keys = message.static_account_keys();
+    let account_key = |index: &u8| account_keys.get(usize::from(*index));
+    message
+        .instructions()
+        .iter()
+        .filter(|instruction| {
+            account_key(&instruction.program_id_index) == Some(&system_program::ID)
+        })
+        .filter_map(|instruction| {
+            let recipient = account_key(instruction.accounts.get(1)?)?;
+            match bincode::deserialize(&instruction.data).ok()? {
+                SystemInstruction::Transfer { lamports } if tip_accounts.contains(recipient) => {
+                    Some(lamports)
+                }
+                _ => None,
+            }
+        })
+        .reduce(u64::saturating_add)
+}
+
+/// Associated token account program, whose setup instructions create the user's token accounts
+pub const ASSOCIATED_TOKEN_PROGRAM_ID: Pubkey =
+    pubkey!("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+/// Number of instructions of each kind around the swap, to plan compute budget and transaction size
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InstructionCounts {
+    pub compute_budget: usize,
+    /// Setup instructions, including the token ledger instruction when present
+    pub setup: usize,
+    pub cleanup: usize,
+    /// Other instructions such as the Jito tip
+    pub other: usize,
+    /// Always set for responses of the API, which require a swap instruction
+    pub has_swap: bool,
+}
+
+impl InstructionCounts {
+    pub fn total(&self) -> usize {
+        self.compute_budget + self.setup + self.cleanup + self.other + usize::from(self.has_swap)
+    }
+}
+
+fn compact_u16_len(value: usize) -> usize {
+    match value {
+        0..=0x7f => 1,
+        0x80..=0x3fff => 2,
+        _ => 3,
+    }
+}
+
+impl SwapInstructionsResponse {
+    /// Instructions in the order they are meant to be assembled into a transaction
+    pub fn instructions(&self) -> impl Iterator<Item = &Instruction> {
+        self.compute_budget_instructions
+            .iter()
+            .chain(&self.token_ledger_instruction)
+            .chain(&self.setup_instructions)
+            .chain(std::iter::once(&self.swap_instruction))
+            .chain(&self.cleanup_instruction)
+            .chain(&self.other_instructions)
+    }
+
+    /// Estimated size of a signed versioned transaction made of [`Self::instructions`], to compare against
+    /// [`PACKET_DATA_SIZE`]. The lookup table contents are unknown offline, so when lookup tables are given
+    /// every account other than signers and programs is assumed to be loaded from them, making this a lower bound.
+    pub fn estimated_transaction_size(&self) -> usize {
+        let mut signers = HashSet::new();
+        let mut programs = HashSet::new();
+        let mut other_accounts = HashSet::new();
+        let mut instructions_size = 0;
+        let mut instruction_count = 0;
+        for instruction in self.instructions() {
+            programs.insert(instruction.program_id);
+            for account in &instruction.accounts {
+                if account.is_signer {
+                    signers.insert(account.pubkey);
+                } else {
+                    other_accounts.insert(account.pubkey);
+                }
+            }
+            instructions_size += 1
+                + compact_u16_len(instruction.accounts.len())
+                + instruction.accounts.len()
+                + compact_u16_len(instruction.data.len())
+                + instruction.data.len();
+            instruction_count += 1;
+        }
+        other_accounts.retain(|account| !signers.contains(account) && !programs.contains(account));
+        programs.retain(|program| !signers.contains(program));
+
+        let signer_count = signers.len().max(1);
+        let lookup_table_count = self.address_lookup_table_addresses.len();
+        let (static_count, looked_up_count) = if lookup_table_count == 0 {
+            (signer_count + programs.len() + other_accounts.len(), 0)
+        } else {
+            (signer_count + programs.len(), other_accounts.len())
+        };
+
+        compact_u16_len(signer_count)
+            + 64 * signer_count
+            // version prefix and message header
+            + 1
+            + 3
+            + compact_u16_len(static_count)
+            + 32 * static_count
+            // recent blockhash
+            + 32
+            + compact_u16_len(instruction_count)
+            + instructions_size
+            + compact_u16_len(lookup_table_count)
+            // lookup table address and writable and readonly index lengths
+            + lookup_table_count * (32 + 2)
+            + looked_up_count
+    }
+
+    /// Accounts the setup instructions create, in instruction order, to disclose them and budget rent.
+    /// Matches associated token account creations, `Create` and `CreateIdempotent`, and system
+    /// `CreateAccount` and `CreateAccountWithSeed` instructions, all of which take the new account
+    /// second. An idempotent creation is listed even though it is a no-op for an existing account.
+    pub fn created_accounts(&self) -> Vec<Pubkey> {
+        self.setup_instructions
+            .iter()
+            .filter(|instruction| {
+                if instruction.program_id == ASSOCIATED_TOKEN_PROGRAM_ID {
+                    // Create carries an empty or zero discriminator, CreateIdempotent a one
+                    matches!(instruction.data.as_slice(), [] | [0] | [1])
+                } else if instruction.program_id == system_program::ID {
+                    matches!(
+                        bincode::deserialize(&instruction.data),
+                        Ok(SystemInstruction::CreateAccount { .. }
+                            | SystemInstruction::CreateAccountWithSeed { .. })
+                    )
+                } else {
+                    false
+                }
+            })
+            .filter_map(|instruction| instruction.accounts.get(1))
+            .map(|account| account.pubkey)
+            .collect()
+    }
+
+    pub fn instruction_counts(&self) -> InstructionCounts {
+        InstructionCounts {
+            compute_budget: self.compute_budget_instructions.len(),
+            setup: self.setup_instructions.len()
+                + usize::from(self.token_ledger_instruction.is_some()),
+            cleanup: usize::from(self.cleanup_instruction.is_some()),
+            other: self.other_instructions.len(),
+            has_swap: true,
+        }
+    }
+}
+
 // Duplicate for deserialization
 #[derive(Deserialize, Debug, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -198,3 +732,600 @@ impl From<SwapInstructionsResponseInternal> for SwapInstructionsResponse {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use serde_json::{json, Value};
+    use solana_sdk::{
+        address_lookup_table::AddressLookupTableAccount,
+        instruction::CompiledInstruction,
+        message::{
+            v0::{self, MessageAddressTableLookup},
+            MessageHeader, VersionedMessage,
+        },
+    };
+
+    use super::*;
+    use crate::transaction_config::PriorityLevel;
+
+    fn quote() -> QuoteResponse {
+        serde_json::from_str(include_str!("../tests/fixtures/quote_exact_in.json")).unwrap()
+    }
+
+    fn shared_accounts_route_data(quote: &QuoteResponse) -> Vec<u8> {
+        let mut data = hashv(&[b"global:", b"shared_accounts_route"]).as_ref()[..8].to_vec();
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(&quote.in_amount.to_le_bytes());
+        data.extend_from_slice(&quote.out_amount.to_le_bytes());
+        data.extend_from_slice(&quote.slippage_bps.to_le_bytes());
+        data.push(0);
+        data
+    }
+
+    /// A v0 swap transaction whose `shared_accounts_route` instruction names `source_mint` and
+    /// `destination_mint` at their account positions, the destination mint being loaded from an
+    /// address lookup table when `destination_mint` is `None`
+    fn route_swap_response(
+        quote: &QuoteResponse,
+        source_mint: Pubkey,
+        destination_mint: Option<Pubkey>,
+    ) -> SwapResponse {
+        let mut account_keys = vec![Pubkey::new_unique(), JUPITER_PROGRAM_ID];
+        let mut accounts = vec![0; 7];
+        account_keys.push(source_mint);
+        accounts.push(2);
+        let mut address_table_lookups = Vec::new();
+        match destination_mint {
+            Some(destination_mint) => {
+                account_keys.push(destination_mint);
+                accounts.push(3);
+            }
+            None => {
+                address_table_lookups.push(MessageAddressTableLookup {
+                    account_key: Pubkey::new_unique(),
+                    writable_indexes: vec![],
+                    readonly_indexes: vec![0],
+                });
+                accounts.push(3);
+            }
+        }
+        let message = v0::Message {
+            header: MessageHeader {
+                num_required_signatures: 1,
+                num_readonly_signed_accounts: 0,
+                num_readonly_unsigned_accounts: 1,
+            },
+            account_keys,
+            recent_blockhash: Hash::default(),
+            instructions: vec![CompiledInstruction::new_from_raw_parts(
+                1,
+                shared_accounts_route_data(quote),
+                accounts,
+            )],
+            address_table_lookups,
+        };
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::default()],
+            message: VersionedMessage::V0(message),
+        };
+        SwapResponse {
+            swap_transaction: bincode::serialize(&transaction).unwrap(),
+            last_valid_block_height: 0,
+            prioritization_fee_lamports: 0,
+            compute_unit_limit: None,
+            prioritization_type: None,
+            dynamic_slippage_report: None,
+            simulation_error: None,
+        }
+    }
+
+    /// A swap response whose serialized transaction is exactly `size` bytes, padding the data of its
+    /// route instruction
+    fn swap_response_of_size(size: usize) -> SwapResponse {
+        let mut swap_response = route_swap_response(&quote(), Pubkey::new_unique(), None);
+        let mut transaction = swap_response.transaction().unwrap();
+        while bincode::serialized_size(&transaction).unwrap() < size as u64 {
+            let VersionedMessage::V0(message) = &mut transaction.message else {
+                unreachable!()
+            };
+            message.instructions[0].data.push(0);
+        }
+        swap_response.swap_transaction = bincode::serialize(&transaction).unwrap();
+        assert_eq!(swap_response.swap_transaction.len(), size);
+        swap_response
+    }
+
+    #[test]
+    fn transaction_at_the_packet_limit_fits() {
+        let swap_response = swap_response_of_size(PACKET_DATA_SIZE);
+        assert_eq!(swap_response.transaction_size_bytes(), 1232);
+        assert_eq!(
+            bincode::serialize(&swap_response.transaction().unwrap())
+                .unwrap()
+                .len(),
+            1232
+        );
+        assert!(!swap_response.exceeds_limit());
+    }
+
+    #[test]
+    fn transaction_one_byte_over_the_packet_limit_exceeds_it() {
+        let swap_response = swap_response_of_size(PACKET_DATA_SIZE + 1);
+        assert_eq!(swap_response.transaction_size_bytes(), 1233);
+        assert!(swap_response.exceeds_limit());
+    }
+
+    #[test]
+    fn to_curl_carries_the_swap_body() {
+        let swap_request = SwapRequest::builder()
+            .user(Pubkey::new_unique())
+            .quote(quote())
+            .build()
+            .unwrap();
+
+        let curl = swap_request.to_curl("https://api.jup.ag/swap/v1").unwrap();
+
+        let (command, body) = curl.split_once(" -d ").unwrap();
+        assert_eq!(
+            command,
+            "curl -X POST 'https://api.jup.ag/swap/v1/swap' -H 'x-api-key: <redacted>' \
+             -H 'Content-Type: application/json'"
+        );
+        let body: serde_json::Value = serde_json::from_str(body.trim_matches('\'')).unwrap();
+        assert_eq!(body, serde_json::to_value(&swap_request).unwrap());
+    }
+
+    #[test]
+    fn shared_quote_is_not_cloned_per_request() {
+        let shared_quote = Arc::new(quote());
+        let swap_requests: Vec<SwapRequest> = (0..3)
+            .map(|_| {
+                SwapRequestBuilder::from_shared_quote(shared_quote.clone())
+                    .user(Pubkey::new_unique())
+                    .build()
+                    .unwrap()
+            })
+            .collect();
+        assert_eq!(Arc::strong_count(&shared_quote), 4);
+        assert!(swap_requests
+            .iter()
+            .all(|swap_request| Arc::ptr_eq(&swap_request.quote_response, &shared_quote)));
+    }
+
+    #[test]
+    fn verify_against_quote_checks_static_mints() {
+        let quote = quote();
+        let swap_response = route_swap_response(&quote, quote.input_mint, Some(quote.output_mint));
+        assert!(swap_response.verify_against_quote(&quote).is_ok());
+
+        let swap_response =
+            route_swap_response(&quote, quote.input_mint, Some(Pubkey::new_unique()));
+        assert!(matches!(
+            swap_response.verify_against_quote(&quote),
+            Err(ClientError::TransactionMismatch(_))
+        ));
+    }
+
+    #[test]
+    fn verify_against_quote_rejects_lookup_table_mints() {
+        let quote = quote();
+        let swap_response = route_swap_response(&quote, quote.input_mint, None);
+        assert!(matches!(
+            swap_response.verify_against_quote(&quote),
+            Err(ClientError::UnverifiableTransaction(_))
+        ));
+    }
+
+    #[test]
+    fn verify_against_quote_rejects_other_amount() {
+        let mut quote = quote();
+        let swap_response = route_swap_response(&quote, quote.input_mint, Some(quote.output_mint));
+        quote.in_amount += 1;
+        assert!(matches!(
+            swap_response.verify_against_quote(&quote),
+            Err(ClientError::TransactionMismatch(_))
+        ));
+    }
+
+    fn swap_instructions_response(
+        address_lookup_table_addresses: Vec<Pubkey>,
+    ) -> SwapInstructionsResponse {
+        let user = Pubkey::new_unique();
+        let token_program = Pubkey::new_unique();
+        let user_account = Pubkey::new_unique();
+        let instruction = |program_id, data_len, accounts: Vec<AccountMeta>| {
+            Instruction::new_with_bytes(program_id, &vec![1; data_len], accounts)
+        };
+        SwapInstructionsResponse {
+            token_ledger_instruction: None,
+            compute_budget_instructions: vec![instruction(Pubkey::new_unique(), 5, vec![])],
+            setup_instructions: vec![instruction(
+                token_program,
+                1,
+                vec![
+                    AccountMeta::new(user, true),
+                    AccountMeta::new(user_account, false),
+                    AccountMeta::new_readonly(token_program, false),
+                ],
+            )],
+            swap_instruction: instruction(
+                JUPITER_PROGRAM_ID,
+                200,
+                std::iter::once(AccountMeta::new_readonly(user, true))
+                    .chain([AccountMeta::new(user_account, false)])
+                    .chain((0..20).map(|_| AccountMeta::new(Pubkey::new_unique(), false)))
+                    .collect(),
+            ),
+            cleanup_instruction: None,
+            other_instructions: vec![],
+            address_lookup_table_addresses,
+            prioritization_fee_lamports: 0,
+            compute_unit_limit: 0,
+            prioritization_type: None,
+            dynamic_slippage_report: None,
+            simulation_error: None,
+        }
+    }
+
+    /// Size of `response` compiled into a signed v0 transaction, loading every account possible from a
+    /// single lookup table when the response has one
+    fn compiled_transaction_size(response: &SwapInstructionsResponse) -> usize {
+        let instructions: Vec<Instruction> = response.instructions().cloned().collect();
+        let payer = response.swap_instruction.accounts[0].pubkey;
+        let lookup_tables: Vec<AddressLookupTableAccount> = response
+            .address_lookup_table_addresses
+            .iter()
+            .map(|key| AddressLookupTableAccount {
+                key: *key,
+                addresses: instructions
+                    .iter()
+                    .flat_map(|instruction| &instruction.accounts)
+                    .map(|account| account.pubkey)
+                    .collect(),
+            })
+            .collect();
+        let message =
+            v0::Message::try_compile(&payer, &instructions, &lookup_tables, Hash::default())
+                .unwrap();
+        let transaction = VersionedTransaction {
+            signatures: vec![
+                Signature::default();
+                usize::from(message.header.num_required_signatures)
+            ],
+            message: VersionedMessage::V0(message),
+        };
+        bincode::serialize(&transaction).unwrap().len()
+    }
+
+    #[test]
+    fn estimated_transaction_size_matches_compiled_transaction() {
+        let response = swap_instructions_response(vec![]);
+        assert_eq!(
+            response.estimated_transaction_size(),
+            compiled_transaction_size(&response)
+        );
+    }
+
+    #[test]
+    fn estimated_transaction_size_loads_accounts_from_lookup_tables() {
+        let response = swap_instructions_response(vec![Pubkey::new_unique()]);
+        assert_eq!(
+            response.estimated_transaction_size(),
+            compiled_transaction_size(&response)
+        );
+        assert!(
+            response.estimated_transaction_size()
+                < swap_instructions_response(vec![]).estimated_transaction_size()
+        );
+    }
+
+    #[test]
+    fn builder_builds_from_a_user_and_a_quote() {
+        let user_public_key = Pubkey::new_unique();
+        let swap_request = SwapRequest::builder()
+            .user(user_public_key)
+            .quote(quote())
+            .build()
+            .unwrap();
+        assert_eq!(swap_request.user_public_key, user_public_key);
+        assert_eq!(
+            serde_json::to_value(&*swap_request.quote_response).unwrap(),
+            serde_json::to_value(quote()).unwrap()
+        );
+        assert_eq!(swap_request.config, TransactionConfig::default());
+
+        assert!(matches!(
+            SwapRequest::builder().quote(quote()).build(),
+            Err(ClientError::InvalidRequest(_))
+        ));
+        assert!(matches!(
+            SwapRequest::builder().user(user_public_key).build(),
+            Err(ClientError::InvalidRequest(_))
+        ));
+    }
+
+    #[test]
+    fn account_count_includes_lookup_table_accounts() {
+        let quote = quote();
+        let static_only = route_swap_response(&quote, quote.input_mint, Some(quote.output_mint));
+        // payer, program, source mint and destination mint
+        assert_eq!(static_only.account_count().unwrap(), 4);
+
+        let with_lookup = route_swap_response(&quote, quote.input_mint, None);
+        // payer, program and source mint, plus the destination mint from the lookup table
+        assert_eq!(with_lookup.account_count().unwrap(), 4);
+        assert_eq!(
+            with_lookup
+                .transaction()
+                .unwrap()
+                .message
+                .static_account_keys()
+                .len(),
+            3
+        );
+    }
+
+    #[test]
+    fn compute_unit_limit_defaults_to_the_maximum_when_absent() {
+        let mut value = json!({
+            "swapTransaction": "",
+            "lastValidBlockHeight": 1,
+            "prioritizationFeeLamports": 0,
+            "computeUnitLimit": 250_000,
+        });
+        let swap_response: SwapResponse = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(swap_response.compute_unit_limit, Some(250_000));
+        assert_eq!(swap_response.compute_unit_limit_or_default(), 250_000);
+
+        value.as_object_mut().unwrap().remove("computeUnitLimit");
+        let swap_response: SwapResponse = serde_json::from_value(value).unwrap();
+        assert_eq!(swap_response.compute_unit_limit, None);
+        assert_eq!(
+            swap_response.compute_unit_limit_or_default(),
+            MAX_COMPUTE_UNIT_LIMIT
+        );
+    }
+
+    #[test]
+    fn auto_wrap_sol_wraps_only_routes_touching_sol() {
+        let usdc = Pubkey::new_unique();
+        let bonk = Pubkey::new_unique();
+        let wraps = |input_mint, output_mint| {
+            SwapRequest::builder()
+                .wrap_and_unwrap_sol(false)
+                .auto_wrap_sol(input_mint, output_mint)
+                .user(Pubkey::new_unique())
+                .quote(quote())
+                .build()
+                .unwrap()
+                .config
+                .wrap_and_unwrap_sol
+        };
+        assert!(wraps(NATIVE_MINT, usdc));
+        assert!(wraps(usdc, NATIVE_MINT));
+        assert!(!wraps(usdc, bonk));
+    }
+
+    /// A `/swap-instructions` response swapping native SOL into USDC, wrapping the input in a new
+    /// WSOL account, creating the USDC account and tipping Jito
+    fn sol_to_usdc_swap_instructions() -> SwapInstructionsResponse {
+        serde_json::from_str::<SwapInstructionsResponseInternal>(include_str!(
+            "../tests/fixtures/swap_instructions.json"
+        ))
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn instruction_counts_of_a_sol_to_usdc_swap() {
+        let counts = sol_to_usdc_swap_instructions().instruction_counts();
+        assert_eq!(
+            counts,
+            InstructionCounts {
+                compute_budget: 2,
+                setup: 4,
+                cleanup: 1,
+                other: 1,
+                has_swap: true,
+            }
+        );
+        assert_eq!(counts.total(), 9);
+    }
+
+    #[test]
+    fn priority_strategies_serialize_as_their_fee_variant() {
+        let cases = [
+            (PriorityStrategy::Auto, json!("auto")),
+            (PriorityStrategy::Fixed(5_000), json!(5_000)),
+            (
+                PriorityStrategy::Dynamic {
+                    level: PriorityLevel::High,
+                    cap: 50_000,
+                },
+                json!({"priorityLevelWithMaxLamports": {
+                    "priorityLevel": "high",
+                    "maxLamports": 50_000,
+                    "global": false,
+                }}),
+            ),
+            (
+                PriorityStrategy::Jito(10_000),
+                json!({"jitoTipLamports": 10_000}),
+            ),
+        ];
+        for (strategy, expected) in cases {
+            let swap_request = SwapRequest::builder()
+                .user(Pubkey::new_unique())
+                .quote(quote())
+                .compute_unit_price_micro_lamports(ComputeUnitPriceMicroLamports::MicroLamports(1))
+                .priority_strategy(strategy)
+                .build()
+                .unwrap();
+            let body = serde_json::to_value(&swap_request).unwrap();
+            assert_eq!(body["prioritizationFeeLamports"], expected, "{strategy:?}");
+            assert!(body.get("computeUnitPriceMicroLamports").is_none());
+        }
+    }
+
+    /// A swap response carrying an unsigned legacy transaction of `instructions`, paid by `payer`
+    fn legacy_swap_response(payer: &Pubkey, instructions: &[Instruction]) -> SwapResponse {
+        let message = solana_sdk::message::Message::new_with_blockhash(
+            instructions,
+            Some(payer),
+            &Hash::new_unique(),
+        );
+        let transaction = VersionedTransaction {
+            signatures: vec![
+                Signature::default();
+                usize::from(message.header.num_required_signatures)
+            ],
+            message: VersionedMessage::Legacy(message),
+        };
+        SwapResponse {
+            swap_transaction: bincode::serialize(&transaction).unwrap(),
+            last_valid_block_height: 0,
+            prioritization_fee_lamports: 0,
+            compute_unit_limit: None,
+            prioritization_type: None,
+            dynamic_slippage_report: None,
+            simulation_error: None,
+        }
+    }
+
+    #[test]
+    fn jito_tip_lamports_sums_transfers_to_tip_accounts() {
+        use solana_sdk::system_instruction::transfer;
+
+        let payer = Pubkey::new_unique();
+        let with_tip = legacy_swap_response(
+            &payer,
+            &[
+                transfer(&payer, &Pubkey::new_unique(), 1_000_000),
+                transfer(&payer, &JITO_TIP_ACCOUNTS[3], 10_000),
+            ],
+        );
+        assert_eq!(with_tip.jito_tip_lamports().unwrap(), Some(10_000));
+
+        let custom_tip_account = Pubkey::new_unique();
+        let custom_tip =
+            legacy_swap_response(&payer, &[transfer(&payer, &custom_tip_account, 5_000)]);
+        assert_eq!(custom_tip.jito_tip_lamports().unwrap(), None);
+        assert_eq!(
+            custom_tip
+                .jito_tip_lamports_to(&[custom_tip_account])
+                .unwrap(),
+            Some(5_000)
+        );
+    }
+
+    #[test]
+    fn quote_from_body_recovers_the_embedded_quote() {
+        let swap_request = SwapRequest::builder()
+            .user(Pubkey::new_unique())
+            .quote(quote())
+            .build()
+            .unwrap();
+        let body = serde_json::to_string(&swap_request).unwrap();
+        let quote_response = SwapRequest::quote_from_body(&body).unwrap();
+        assert_eq!(
+            serde_json::to_value(quote_response).unwrap(),
+            serde_json::to_value(quote()).unwrap()
+        );
+
+        // Invalid config fields do not get in the way
+        let mut body: Value = serde_json::from_str(&body).unwrap();
+        body["prioritizationFeeLamports"] = json!({"unknownMode": true});
+        assert!(SwapRequest::quote_from_body(&body.to_string()).is_ok());
+        assert!(SwapRequest::quote_from_body("{}").is_err());
+    }
+
+    #[test]
+    fn with_blockhash_replaces_the_blockhash_and_clears_signatures() {
+        use solana_sdk::{signature::Keypair, signer::Signer, system_instruction::transfer};
+
+        let payer = Keypair::new();
+        let mut swap_response = legacy_swap_response(
+            &payer.pubkey(),
+            &[transfer(&payer.pubkey(), &Pubkey::new_unique(), 1)],
+        );
+        let original =
+            VersionedTransaction::try_new(swap_response.transaction().unwrap().message, &[&payer])
+                .unwrap();
+        swap_response.swap_transaction = bincode::serialize(&original).unwrap();
+        let blockhash = Hash::new_unique();
+
+        let transaction = swap_response.with_blockhash(blockhash).unwrap();
+        assert_eq!(*transaction.message.recent_blockhash(), blockhash);
+        assert_ne!(*original.message.recent_blockhash(), blockhash);
+        assert_eq!(
+            transaction.message.instructions(),
+            original.message.instructions()
+        );
+        assert!(transaction
+            .signatures
+            .iter()
+            .all(|signature| *signature == Signature::default()));
+
+        let signed = VersionedTransaction::try_new(transaction.message, &[&payer]).unwrap();
+        assert_eq!(*signed.message.recent_blockhash(), blockhash);
+        assert!(signed
+            .verify_with_results()
+            .iter()
+            .all(|verified| *verified));
+    }
+
+    #[test]
+    fn required_signatures_counts_every_signer() {
+        let payer = Pubkey::new_unique();
+        let single_signer = legacy_swap_response(
+            &payer,
+            &[solana_sdk::system_instruction::transfer(
+                &payer,
+                &Pubkey::new_unique(),
+                1,
+            )],
+        );
+        assert_eq!(single_signer.required_signatures().unwrap(), 1);
+
+        let co_signer = Pubkey::new_unique();
+        let co_signed = legacy_swap_response(
+            &payer,
+            &[Instruction::new_with_bytes(
+                Pubkey::new_unique(),
+                &[],
+                vec![
+                    AccountMeta::new(payer, true),
+                    AccountMeta::new_readonly(co_signer, true),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                ],
+            )],
+        );
+        assert_eq!(co_signed.required_signatures().unwrap(), 2);
+    }
+
+    #[test]
+    fn created_accounts_lists_token_account_creations() {
+        let mut swap_instructions = sol_to_usdc_swap_instructions();
+        assert_eq!(
+            swap_instructions.created_accounts(),
+            vec![
+                pubkey!("Coz3LBGDD5czMN8CroEdxrDykE9CSnXBMyj7SZMwh82z"),
+                pubkey!("7EJSueeCjseYzghxU2XhcGEUn7RJDh43Z2dL6dvGy9mw"),
+            ]
+        );
+
+        let user = pubkey!("US517G5965aydkZ46HS38QLi7UQiSojurfbQfKCELFx");
+        let new_account = Pubkey::new_unique();
+        swap_instructions
+            .setup_instructions
+            .push(solana_sdk::system_instruction::create_account(
+                &user,
+                &new_account,
+                2_039_280,
+                165,
+                &Pubkey::new_unique(),
+            ));
+        assert_eq!(swap_instructions.created_accounts().len(), 3);
+        assert_eq!(swap_instructions.created_accounts()[2], new_account);
+    }
+}
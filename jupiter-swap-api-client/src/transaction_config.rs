@@ -1,9 +1,13 @@
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use solana_account_decoder::UiAccount;
-use solana_sdk::pubkey::Pubkey;
+use solana_sdk::{pubkey, pubkey::Pubkey};
 
-use crate::serde_helpers::option_field_as_string;
+use crate::quote::QuoteRequest;
+use crate::serde_helpers::{field_as_string, option_field_as_string};
+
+/// 包装SOL（WSOL）的代币铸币地址，即`wrap_and_unwrap_sol`所包装和解包的代币
+pub const NATIVE_MINT: Pubkey = pubkey!("So11111111111111111111111111111111111111112");
 
 #[derive(Deserialize, Serialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "camelCase")]
@@ -14,7 +18,8 @@ pub enum ComputeUnitPriceMicroLamports {
     Auto,
 }
 
-#[derive(Serialize, Deserialize, Debug, PartialEq, Copy, Clone)]
+/// 优先级从低到高排序：Medium < High < VeryHigh
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
 #[serde(rename_all = "camelCase")]
 pub enum PriorityLevel {
     Medium,
@@ -22,6 +27,19 @@ pub enum PriorityLevel {
     VeryHigh,
 }
 
+impl PriorityLevel {
+    /// 返回下一个更高的优先级，在VeryHigh处饱和，便于重试时逐级提高费用
+    pub fn next(self) -> Self {
+        match self {
+            Self::Medium => Self::High,
+            Self::High | Self::VeryHigh => Self::VeryHigh,
+        }
+    }
+}
+
+/// 可解析的形式：`"auto"`、`"disabled"`、lamports数字或数字字符串、
+/// `{"autoMultiplier": n}`、`{"jitoTipLamports": n}`，
+/// 以及`{"priorityLevelWithMaxLamports": {"priorityLevel", "maxLamports", "global"}}`
 #[derive(Deserialize, Debug, PartialEq, Copy, Clone, Default)]
 #[serde(rename_all = "camelCase")]
 pub enum PrioritizationFeeLamports {
@@ -37,12 +55,62 @@ pub enum PrioritizationFeeLamports {
     #[default]
     #[serde(untagged, deserialize_with = "auto")]
     Auto,
-    #[serde(untagged)]
+    #[serde(untagged, deserialize_with = "lamports")]
     Lamports(u64),
     #[serde(untagged, deserialize_with = "disabled")]
     Disabled,
 }
 
+impl PrioritizationFeeLamports {
+    /// 尽力估算优先级费用（lamports），便于日志记录和预算：
+    /// - `Lamports`和`JitoTipLamports`为精确值；
+    /// - `AutoMultiplier`假设`base_fee`为自动模式下每个计算单元的价格（micro-lamports），
+    ///   估算值 = `compute_units` * `base_fee` * 倍数 / 1_000_000，向上取整；
+    /// - `PriorityLevelWithMaxLamports`返回`max_lamports`作为上限；
+    /// - `Auto`和`Disabled`无法估算，返回`None`。
+    pub fn estimated_lamports(&self, compute_units: u32, base_fee: u64) -> Option<u64> {
+        match self {
+            Self::Lamports(lamports) | Self::JitoTipLamports(lamports) => Some(*lamports),
+            Self::AutoMultiplier(multiplier) => {
+                let micro_lamports =
+                    u128::from(compute_units) * u128::from(base_fee) * u128::from(*multiplier);
+                Some(u64::try_from(micro_lamports.div_ceil(1_000_000)).unwrap_or(u64::MAX))
+            }
+            Self::PriorityLevelWithMaxLamports { max_lamports, .. } => Some(*max_lamports),
+            Self::Auto | Self::Disabled => None,
+        }
+    }
+}
+
+/// [`PrioritizationFeeLamports`]之上的高层优先级费用策略，
+/// 通过`SwapRequestBuilder::priority_strategy`设置时会同时清除`compute_unit_price_micro_lamports`，避免两者冲突
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum PriorityStrategy {
+    /// 由API自动决定优先级费用
+    Auto,
+    /// 固定的优先级费用（lamports）
+    Fixed(u64),
+    /// 按优先级估算费用，并以`cap`（lamports）为上限
+    Dynamic { level: PriorityLevel, cap: u64 },
+    /// 以Jito小费代替优先级费用（lamports）
+    Jito(u64),
+}
+
+impl From<PriorityStrategy> for PrioritizationFeeLamports {
+    fn from(strategy: PriorityStrategy) -> Self {
+        match strategy {
+            PriorityStrategy::Auto => Self::Auto,
+            PriorityStrategy::Fixed(lamports) => Self::Lamports(lamports),
+            PriorityStrategy::Dynamic { level, cap } => Self::PriorityLevelWithMaxLamports {
+                priority_level: level,
+                max_lamports: cap,
+                global: false,
+            },
+            PriorityStrategy::Jito(tip) => Self::JitoTipLamports(tip),
+        }
+    }
+}
+
 impl Serialize for PrioritizationFeeLamports {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
@@ -115,6 +183,21 @@ where
     Ok(())
 }
 
+fn lamports<'de, D>(deserializer: D) -> Result<u64, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Helper {
+        Number(u64),
+        String(#[serde(with = "field_as_string")] u64),
+    }
+    match Helper::deserialize(deserializer)? {
+        Helper::Number(lamports) | Helper::String(lamports) => Ok(lamports),
+    }
+}
+
 fn disabled<'de, D>(deserializer: D) -> Result<(), D::Error>
 where
     D: Deserializer<'de>,
@@ -135,65 +218,90 @@ pub struct DynamicSlippageSettings {
     pub max_bps: Option<u16>,
 }
 
+/// 序列化时省略取默认值的字段，默认配置序列化为`{}`。
+/// `wrap_and_unwrap_sol`默认为true，因此仅在为false时才会发送。
 #[derive(Serialize, Deserialize, Debug, PartialEq, Clone)]
 #[serde(rename_all = "camelCase")]
 #[serde(default)]
 pub struct TransactionConfig {
     /// 包装和解包SOL。如果设置了`destination_token_account`，该选项将被忽略，
     /// 因为`destination_token_account`可能属于其他用户，我们无权关闭该账户。
+    #[serde(skip_serializing_if = "is_true")]
     pub wrap_and_unwrap_sol: bool,
     /// 允许使用优化的WSOL代币账户方案：通过transfer、assign with seed、allocate with seed
     /// 然后初始化account 3来代替昂贵的关联代币账户创建过程。
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub allow_optimized_wrapped_sol_token_account: bool,
     /// 输出代币的费用代币账户，通过种子 = ["referral_ata", referral_account, mint] 
     /// 和`REFER4ZgmyYx9c6He5XfaTMiGfdLwRnkV4RPp9t9iF3`推荐合约派生
     /// (仅在设置了feeBps且确保feeAccount已创建时传入)
-    #[serde(with = "option_field_as_string")]
+    #[serde(
+        with = "option_field_as_string",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub fee_account: Option<Pubkey>,
     /// 用于接收交换输出代币的代币账户公钥。如未提供，将使用用户的ATA（关联代币账户）。
     /// 如果提供，我们假定该代币账户已初始化。
-    #[serde(with = "option_field_as_string")]
+    #[serde(
+        with = "option_field_as_string",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub destination_token_account: Option<Pubkey>,
     /// 添加一个只读、非签名的跟踪账户，该账户不被Jupiter使用
-    #[serde(with = "option_field_as_string")]
+    #[serde(
+        with = "option_field_as_string",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub tracking_account: Option<Pubkey>,
     /// 计算单元价格，用于交易优先级排序，额外费用 = 消耗的计算单元 * computeUnitPriceMicroLamports
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub compute_unit_price_micro_lamports: Option<ComputeUnitPriceMicroLamports>,
     /// 除签名费外，为交易支付的优先级费用（lamports）。
     /// 与`compute_unit_price_micro_lamports`互斥，不可同时使用。
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub prioritization_fee_lamports: Option<PrioritizationFeeLamports>,
     /// 启用后，将执行交换模拟以获取使用的计算单元，并在ComputeBudget中设置计算单元限制。
     /// 由于需要额外进行一次RPC调用来模拟，这会略微增加延迟。默认为false。
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub dynamic_compute_unit_limit: bool,
     /// 请求使用传统交易而非默认的版本化交易。需要与使用asLegacyTransaction的报价配对使用，
     /// 否则交易可能过大。
     ///
     /// 默认值: false
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub as_legacy_transaction: bool,
     /// 启用共享程序账户的使用。这意味着不需要创建中间代币账户或开放订单账户。
     /// 但同时热门账户的可能性也会更高。
     ///
     /// 默认值: 内部优化决定
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub use_shared_accounts: Option<bool>,
     /// 当交换前的指令包含转账操作从而增加输入代币数量时，此选项非常有用。
     /// 交换将仅使用代币账本记录的数量与当前代币数量之间的差额。
     ///
     /// 默认值: false
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub use_token_ledger: bool,
     /// 跳过RPC调用，并假设用户账户不存在。
     /// 因此，所有设置指令都会被填充，但不会为用户相关账户（代币账户、Openbook开放订单等）进行RPC调用。
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub skip_user_accounts_rpc_calls: bool,
     /// 提供带键的UI账户允许加载不在市场缓存中的AMM。
     /// 如果一个带键的UI账户是AMM状态，必须按照市场缓存格式提供其参数。
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub keyed_ui_accounts: Option<Vec<KeyedUiAccount>>,
     /// 程序授权ID
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub program_authority_id: Option<u8>,
     /// 动态滑点设置
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub dynamic_slippage: Option<DynamicSlippageSettings>,
     /// 区块哈希过期前的剩余插槽数
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub blockhash_slots_to_expiry: Option<u8>,
     /// 请求正确的最后一个有效区块高度，
     /// 这是为了让所有消费者能平滑过渡到agave 2.0，参见 https://github.com/solana-labs/solana/issues/24526
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
     pub correct_last_valid_block_height: bool,
 }
 
@@ -222,6 +330,82 @@ impl Default for TransactionConfig {
     }
 }
 
+impl TransactionConfig {
+    /// 支付场景的预设，与[`QuoteRequest::payment_preset`](crate::quote::QuoteRequest::payment_preset)配合使用：
+    /// - `destination_token_account`设为收款方的代币账户，该账户必须已初始化；
+    /// - 保持`wrap_and_unwrap_sol`开启，以原生SOL付款时由交换自动包装，付款方无需预先持有WSOL代币账户，
+    ///   [`Self::validate_for_mints`]因此不会报错。
+    pub fn payment_preset(destination_token_account: Pubkey) -> Self {
+        Self {
+            destination_token_account: Some(destination_token_account),
+            ..Self::default()
+        }
+    }
+
+    /// 以报价请求中与交易共用的参数为基础构建配置，其余字段保持默认值，避免两者不一致：
+    /// - `as_legacy_transaction`：报价请求为传统交易时，交易也使用传统交易。
+    ///
+    /// 设置了`platform_fee_bps`时仍需自行设置`fee_account`，该账户无法从报价请求推导。
+    pub fn from_quote_request(quote_request: &QuoteRequest) -> Self {
+        Self {
+            as_legacy_transaction: quote_request.as_legacy_transaction.unwrap_or(false),
+            ..Self::default()
+        }
+    }
+
+    /// 检查关闭`wrap_and_unwrap_sol`时涉及原生SOL的常见错误：
+    /// - 输入为[`NATIVE_MINT`]时，交换只能使用用户已有的WSOL代币账户，没有该账户或余额不足时交换会失败；
+    /// - 输出为[`NATIVE_MINT`]且未设置`destination_token_account`时，输出留在用户的WSOL代币账户中而不会解包。
+    ///
+    /// 已自行管理WSOL代币账户的用户可以忽略该错误。
+    pub fn validate_for_mints(
+        &self,
+        input_mint: &Pubkey,
+        output_mint: &Pubkey,
+    ) -> Result<(), String> {
+        if self.wrap_and_unwrap_sol {
+            return Ok(());
+        }
+        if *input_mint == NATIVE_MINT {
+            return Err(String::from(
+                "input is native SOL but wrap_and_unwrap_sol is disabled, \
+                 the swap requires an existing funded WSOL token account",
+            ));
+        }
+        if *output_mint == NATIVE_MINT && self.destination_token_account.is_none() {
+            return Err(String::from(
+                "output is native SOL but wrap_and_unwrap_sol is disabled and no \
+                 destination_token_account is set, the output stays wrapped in a WSOL token account",
+            ));
+        }
+        Ok(())
+    }
+
+    /// 按文档约定的优先级解析WSOL相关选项，使实际生效的配置显式化：
+    /// - 设置了`destination_token_account`时，`wrap_and_unwrap_sol`会被忽略，因此将其清除；
+    /// - 不包装SOL时，`allow_optimized_wrapped_sol_token_account`没有意义，因此将其清除。
+    ///
+    /// 覆盖用户设置时会输出警告日志。
+    pub fn normalize(&mut self) {
+        if self.destination_token_account.is_some() && self.wrap_and_unwrap_sol {
+            log::warn!(
+                "wrap_and_unwrap_sol is ignored when destination_token_account is set, disabling it"
+            );
+            self.wrap_and_unwrap_sol = false;
+        }
+        if !self.wrap_and_unwrap_sol && self.allow_optimized_wrapped_sol_token_account {
+            log::warn!(
+                "allow_optimized_wrapped_sol_token_account has no effect without wrap_and_unwrap_sol, disabling it"
+            );
+            self.allow_optimized_wrapped_sol_token_account = false;
+        }
+    }
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
+}
+
 #[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
 pub struct KeyedUiAccount {
     pub pubkey: String,
@@ -230,3 +414,230 @@ pub struct KeyedUiAccount {
     /// Additional data an Amm requires, Amm dependent and decoded in the Amm implementation
     pub params: Option<Value>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::quote::SwapMode;
+    use serde_json::json;
+
+    #[test]
+    fn normalize_resolves_every_wsol_flag_combination() {
+        // (destination_token_account, wrap_and_unwrap_sol, allow_optimized_wrapped_sol_token_account)
+        // and the expected (wrap_and_unwrap_sol, allow_optimized_wrapped_sol_token_account)
+        let cases = [
+            ((false, false, false), (false, false)),
+            ((false, false, true), (false, false)),
+            ((false, true, false), (true, false)),
+            ((false, true, true), (true, true)),
+            ((true, false, false), (false, false)),
+            ((true, false, true), (false, false)),
+            ((true, true, false), (false, false)),
+            ((true, true, true), (false, false)),
+        ];
+        for ((destination, wrap, allow_optimized), expected) in cases {
+            let destination_token_account = destination.then(Pubkey::new_unique);
+            let mut config = TransactionConfig {
+                destination_token_account,
+                wrap_and_unwrap_sol: wrap,
+                allow_optimized_wrapped_sol_token_account: allow_optimized,
+                ..TransactionConfig::default()
+            };
+            config.normalize();
+            assert_eq!(
+                (
+                    config.wrap_and_unwrap_sol,
+                    config.allow_optimized_wrapped_sol_token_account
+                ),
+                expected,
+                "destination {destination}, wrap {wrap}, allow optimized {allow_optimized}"
+            );
+            assert_eq!(config.destination_token_account, destination_token_account);
+        }
+    }
+
+    #[test]
+    fn normalize_leaves_the_default_untouched() {
+        let mut config = TransactionConfig::default();
+        config.normalize();
+        assert_eq!(config, TransactionConfig::default());
+    }
+
+    #[test]
+    fn payment_presets_pay_an_exact_amount_in_native_sol() {
+        let usdc = Pubkey::new_unique();
+        let quote_request = QuoteRequest::payment_preset(NATIVE_MINT, usdc, 25_000_000);
+        assert_eq!(quote_request.swap_mode, Some(SwapMode::ExactOut));
+        assert_eq!(quote_request.amount, 25_000_000);
+        assert_eq!(quote_request.auto_slippage, Some(false));
+
+        let destination_token_account = Pubkey::new_unique();
+        let config = TransactionConfig::payment_preset(destination_token_account);
+        assert!(config.wrap_and_unwrap_sol);
+        assert_eq!(
+            config.destination_token_account,
+            Some(destination_token_account)
+        );
+        assert_eq!(config.validate_for_mints(&NATIVE_MINT, &usdc), Ok(()));
+        assert_eq!(config.validate_for_mints(&usdc, &NATIVE_MINT), Ok(()));
+    }
+
+    #[test]
+    fn priority_levels_order_and_escalate_up_to_very_high() {
+        assert!(PriorityLevel::Medium < PriorityLevel::High);
+        assert!(PriorityLevel::High < PriorityLevel::VeryHigh);
+        assert_eq!(PriorityLevel::Medium.next(), PriorityLevel::High);
+        assert_eq!(PriorityLevel::High.next(), PriorityLevel::VeryHigh);
+        assert_eq!(PriorityLevel::VeryHigh.next(), PriorityLevel::VeryHigh);
+    }
+
+    #[test]
+    fn estimated_lamports_covers_every_variant() {
+        assert_eq!(
+            PrioritizationFeeLamports::Lamports(5_000).estimated_lamports(200_000, 10),
+            Some(5_000)
+        );
+        assert_eq!(
+            PrioritizationFeeLamports::JitoTipLamports(10_000).estimated_lamports(200_000, 10),
+            Some(10_000)
+        );
+        // 200_000 CU * 10 micro-lamports * 3 = 6_000_000 micro-lamports
+        assert_eq!(
+            PrioritizationFeeLamports::AutoMultiplier(3).estimated_lamports(200_000, 10),
+            Some(6)
+        );
+        // 1 CU * 1 micro-lamport rounds up to one lamport
+        assert_eq!(
+            PrioritizationFeeLamports::AutoMultiplier(1).estimated_lamports(1, 1),
+            Some(1)
+        );
+        assert_eq!(
+            PrioritizationFeeLamports::PriorityLevelWithMaxLamports {
+                priority_level: PriorityLevel::High,
+                max_lamports: 50_000,
+                global: false,
+            }
+            .estimated_lamports(200_000, 10),
+            Some(50_000)
+        );
+        assert_eq!(
+            PrioritizationFeeLamports::Auto.estimated_lamports(200_000, 10),
+            None
+        );
+        assert_eq!(
+            PrioritizationFeeLamports::Disabled.estimated_lamports(200_000, 10),
+            None
+        );
+    }
+
+    #[test]
+    fn prioritization_fee_lamports_parses_every_shape() {
+        let cases = [
+            (json!(5_000), PrioritizationFeeLamports::Lamports(5_000)),
+            (json!("5000"), PrioritizationFeeLamports::Lamports(5_000)),
+            (json!("auto"), PrioritizationFeeLamports::Auto),
+            (json!("disabled"), PrioritizationFeeLamports::Disabled),
+            (
+                json!({"autoMultiplier": 2}),
+                PrioritizationFeeLamports::AutoMultiplier(2),
+            ),
+            (
+                json!({"jitoTipLamports": 10_000}),
+                PrioritizationFeeLamports::JitoTipLamports(10_000),
+            ),
+            (
+                json!({"priorityLevelWithMaxLamports": {"priorityLevel": "veryHigh", "maxLamports": 50_000}}),
+                PrioritizationFeeLamports::PriorityLevelWithMaxLamports {
+                    priority_level: PriorityLevel::VeryHigh,
+                    max_lamports: 50_000,
+                    global: false,
+                },
+            ),
+        ];
+        for (value, expected) in cases {
+            assert_eq!(
+                serde_json::from_value::<PrioritizationFeeLamports>(value.clone()).unwrap(),
+                expected,
+                "{value}"
+            );
+        }
+
+        assert!(serde_json::from_value::<PrioritizationFeeLamports>(json!("5k")).is_err());
+        assert!(serde_json::from_value::<PrioritizationFeeLamports>(json!(-1)).is_err());
+    }
+
+    #[test]
+    fn default_config_serializes_to_an_empty_object() {
+        assert_eq!(
+            serde_json::to_value(TransactionConfig::default()).unwrap(),
+            json!({})
+        );
+
+        let config = TransactionConfig {
+            wrap_and_unwrap_sol: false,
+            dynamic_compute_unit_limit: true,
+            ..TransactionConfig::default()
+        };
+        assert_eq!(
+            serde_json::to_value(&config).unwrap(),
+            json!({"wrapAndUnwrapSol": false, "dynamicComputeUnitLimit": true})
+        );
+        assert_eq!(
+            serde_json::from_value::<TransactionConfig>(json!({})).unwrap(),
+            TransactionConfig::default()
+        );
+    }
+
+    #[test]
+    fn from_quote_request_follows_the_legacy_transaction_flag() {
+        let quote_request = QuoteRequest {
+            as_legacy_transaction: Some(true),
+            ..QuoteRequest::default()
+        };
+        let config = TransactionConfig::from_quote_request(&quote_request);
+        assert!(config.as_legacy_transaction);
+        assert_eq!(
+            config,
+            TransactionConfig {
+                as_legacy_transaction: true,
+                ..TransactionConfig::default()
+            }
+        );
+
+        for as_legacy_transaction in [None, Some(false)] {
+            let quote_request = QuoteRequest {
+                as_legacy_transaction,
+                ..QuoteRequest::default()
+            };
+            assert_eq!(
+                TransactionConfig::from_quote_request(&quote_request),
+                TransactionConfig::default()
+            );
+        }
+    }
+
+    #[test]
+    fn validate_for_mints_without_wrapping_sol() {
+        let usdc = Pubkey::new_unique();
+        let config = TransactionConfig {
+            wrap_and_unwrap_sol: false,
+            ..Default::default()
+        };
+        assert!(config.validate_for_mints(&NATIVE_MINT, &usdc).is_err());
+        assert!(config.validate_for_mints(&usdc, &NATIVE_MINT).is_err());
+        assert!(config
+            .validate_for_mints(&usdc, &Pubkey::new_unique())
+            .is_ok());
+
+        let config = TransactionConfig {
+            destination_token_account: Some(Pubkey::new_unique()),
+            ..config
+        };
+        assert!(config.validate_for_mints(&usdc, &NATIVE_MINT).is_ok());
+        assert!(config.validate_for_mints(&NATIVE_MINT, &usdc).is_err());
+
+        let wrapping = TransactionConfig::default();
+        assert!(wrapping.wrap_and_unwrap_sol);
+        assert!(wrapping.validate_for_mints(&NATIVE_MINT, &usdc).is_ok());
+    }
+}